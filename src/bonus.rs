@@ -0,0 +1,166 @@
+use cosmwasm_std::{StdError, Uint128};
+use secret_toolkit::snip721::{ Metadata, NftDossier, Trait };
+use crate::error::ContractError;
+use crate::msg::{ Quest, BonusRule, MatchMode, TraitCondition, TraitComparison, WolfProgress };
+
+/// Collects the attributes of both the public and the private metadata of a token
+pub fn dossier_traits(dossier: &NftDossier) -> Vec<Trait> {
+    let mut traits: Vec<Trait> = Vec::new();
+    for metadata in [&dossier.public_metadata, &dossier.private_metadata] {
+        if let Some(Metadata { extension: Some(ext), .. }) = metadata {
+            if let Some(attributes) = &ext.attributes {
+                traits.extend(attributes.iter().cloned());
+            }
+        }
+    }
+    traits
+}
+
+/// Replaces the XP and level traits with the wolf's progress from the contract's
+/// ledger, the metadata may be stale or edited
+pub fn with_progress(mut traits: Vec<Trait>, progress: &WolfProgress, xp_trait: &str, level_trait: &str) -> Vec<Trait> {
+    traits.retain(|t| !matches!(t.trait_type.as_deref(), Some(x) if x == xp_trait || x == level_trait));
+    for (trait_type, value) in [(xp_trait, progress.xp), (level_trait, progress.level)] {
+        traits.push(Trait {
+            display_type: None,
+            trait_type: Some(trait_type.to_string()),
+            value: value.to_string(),
+            max_value: None
+        });
+    }
+    traits
+}
+
+/// Sums the bonus of every rule of the quest the traits match, capped by the
+/// quest's max_bonus_reward
+pub fn bonus_for_traits(quest: &Quest, traits: &[Trait]) -> Result<Uint128, ContractError> {
+    let mut total = Uint128::zero();
+    for rule in quest.bonus_rules.iter() {
+        if rule_matches(rule, traits) {
+            total = total.checked_add(rule.shill_bonus).map_err(StdError::from)?;
+        }
+    }
+
+    Ok(match quest.max_bonus_reward {
+        Some(cap) => std::cmp::min(total, cap),
+        None => total
+    })
+}
+
+/// A rule without conditions never matches
+pub fn rule_matches(rule: &BonusRule, traits: &[Trait]) -> bool {
+    if rule.conditions.is_empty() {
+        return false;
+    }
+
+    for condition in rule.conditions.iter() {
+        let matched = condition_matches(condition, traits);
+        match rule.match_mode {
+            MatchMode::Any if matched => return true,
+            MatchMode::All if !matched => return false,
+            _ => {}
+        }
+    }
+    rule.match_mode == MatchMode::All
+}
+
+/// A condition matches when any trait of its trait_type compares successfully
+pub fn condition_matches(condition: &TraitCondition, traits: &[Trait]) -> bool {
+    traits.iter()
+        .filter(|t| t.trait_type.as_deref() == Some(condition.trait_type.as_str()))
+        .any(|t| compare(&t.value, &condition.comparison, &condition.value))
+}
+
+/// Numeric comparisons never match values that aren't numbers
+fn compare(actual: &str, comparison: &TraitComparison, expected: &str) -> bool {
+    let numbers = match (actual.trim().parse::<i64>(), expected.trim().parse::<i64>()) {
+        (Ok(a), Ok(e)) => Some((a, e)),
+        _ => None
+    };
+    match comparison {
+        TraitComparison::Eq => actual == expected,
+        TraitComparison::Ne => actual != expected,
+        TraitComparison::Gt => matches!(numbers, Some((a, e)) if a > e),
+        TraitComparison::Gte => matches!(numbers, Some((a, e)) if a >= e),
+        TraitComparison::Lt => matches!(numbers, Some((a, e)) if a < e),
+        TraitComparison::Lte => matches!(numbers, Some((a, e)) if a <= e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(trait_type: &str, value: &str) -> Trait {
+        Trait {
+            display_type: None,
+            trait_type: Some(trait_type.to_string()),
+            value: value.to_string(),
+            max_value: None
+        }
+    }
+
+    fn cond(trait_type: &str, comparison: TraitComparison, value: &str) -> TraitCondition {
+        TraitCondition { trait_type: trait_type.to_string(), comparison, value: value.to_string() }
+    }
+
+    fn rule(conditions: Vec<TraitCondition>, match_mode: MatchMode, bonus: u32) -> BonusRule {
+        BonusRule { conditions, match_mode, shill_bonus: Uint128::from(bonus) }
+    }
+
+    fn quest(bonus_rules: Vec<BonusRule>, max_bonus_reward: Option<Uint128>) -> Quest {
+        Quest { bonus_rules, max_bonus_reward, ..Quest::default() }
+    }
+
+    #[test]
+    fn numeric_comparisons() {
+        let traits = vec![t("LVL", "5")];
+        assert!(condition_matches(&cond("LVL", TraitComparison::Gte, "5"), &traits));
+        assert!(condition_matches(&cond("LVL", TraitComparison::Gt, "4"), &traits));
+        assert!(!condition_matches(&cond("LVL", TraitComparison::Gt, "5"), &traits));
+        assert!(condition_matches(&cond("LVL", TraitComparison::Lte, "5"), &traits));
+        assert!(!condition_matches(&cond("LVL", TraitComparison::Lt, "5"), &traits));
+        // values that aren't numbers never match a numeric comparison
+        assert!(!condition_matches(&cond("LVL", TraitComparison::Gte, "high"), &traits));
+        assert!(!condition_matches(&cond("Fur", TraitComparison::Gte, "1"), &[t("Fur", "Grey")]));
+    }
+
+    #[test]
+    fn exact_comparisons() {
+        let traits = vec![t("Fur", "Grey")];
+        assert!(condition_matches(&cond("Fur", TraitComparison::Eq, "Grey"), &traits));
+        assert!(!condition_matches(&cond("Fur", TraitComparison::Eq, "White"), &traits));
+        assert!(condition_matches(&cond("Fur", TraitComparison::Ne, "White"), &traits));
+        assert!(!condition_matches(&cond("Eyes", TraitComparison::Ne, "White"), &traits));
+    }
+
+    #[test]
+    fn any_and_all_matching() {
+        let traits = vec![t("Fur", "Grey"), t("LVL", "3")];
+        let conditions = vec![cond("Fur", TraitComparison::Eq, "Grey"), cond("LVL", TraitComparison::Gte, "5")];
+        assert!(rule_matches(&rule(conditions.clone(), MatchMode::Any, 1), &traits));
+        assert!(!rule_matches(&rule(conditions, MatchMode::All, 1), &traits));
+        assert!(!rule_matches(&rule(vec![], MatchMode::All, 1), &traits));
+    }
+
+    #[test]
+    fn bonuses_stack_up_to_the_cap() {
+        let traits = vec![t("Fur", "Grey"), t("LVL", "7")];
+        let rules = vec![
+            rule(vec![cond("Fur", TraitComparison::Eq, "Grey")], MatchMode::Any, 5),
+            rule(vec![cond("LVL", TraitComparison::Gte, "5")], MatchMode::Any, 8),
+            rule(vec![cond("Eyes", TraitComparison::Eq, "Red")], MatchMode::Any, 100)
+        ];
+        assert_eq!(Uint128::from(13u32), bonus_for_traits(&quest(rules.clone(), None), &traits).unwrap());
+        assert_eq!(Uint128::from(10u32), bonus_for_traits(&quest(rules, Some(Uint128::from(10u32))), &traits).unwrap());
+        assert_eq!(Uint128::zero(), bonus_for_traits(&quest(vec![], None), &traits).unwrap());
+    }
+
+    #[test]
+    fn overflowing_bonuses_are_an_error() {
+        let traits = vec![t("Fur", "Grey")];
+        let mut big = rule(vec![cond("Fur", TraitComparison::Eq, "Grey")], MatchMode::Any, 0);
+        big.shill_bonus = Uint128::MAX;
+        assert!(bonus_for_traits(&quest(vec![big.clone(), big], None), &traits).is_err());
+    }
+}
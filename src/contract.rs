@@ -1,20 +1,27 @@
 use cosmwasm_std::{
     entry_point, to_binary, Env, Deps, DepsMut,
     MessageInfo, Response, StdError, StdResult, Addr, CanonicalAddr,
-    Binary, Uint128, CosmosMsg
+    Binary, Uint128, Uint64, CosmosMsg, Storage
 };
 use crate::error::ContractError;
-use crate::msg::{QuestResponse, ExecuteMsg, InstantiateMsg, QueryMsg, Quest, ContractInfo, QuestMsg, Token, HistoryToken };
-use crate::state::{ State, ADMIN_VIEWING_KEY_ITEM, VIEWING_KEY_STORE,
-    CONFIG_ITEM, LEVEL_ITEM, ADMIN_ITEM, STAKED_NFTS_STORE, STAKED_NFTS_HISTORY_STORE, MY_ADDRESS_ITEM, PREFIX_REVOKED_PERMITS};
-use crate::rand::{sha_256};
+use crate::msg::{QuestResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, Quest, ContractInfo, QuestMsg, Token, HistoryToken, Level, LevelsResponse, Collection, Party, HuntOutcome, LootDrop, FeeDestination, ReceiveMsg, SolvencyResponse, QuestUpdate, WolfProgress, WolfProgressResponse, TokenStatusResponse, QuestParticipantsResponse, UserQuery, ExecuteAnswer, Role };
+use crate::state::{ State, EntryFeeEscrow, Funds, RoleGrant, ADMIN_VIEWING_KEY_ITEM, viewing_key_store, quests_store,
+    CONFIG_ITEM, LEVEL_ITEM, ADMIN_ITEM, staked_nfts_store, MY_ADDRESS_ITEM, PREFIX_REVOKED_PERMITS,
+    CONTRACT_VERSION, CONTRACT_VERSION_ITEM, PRNG_SEED_ITEM, FUNDS_ITEM, parties_store, PARTY_COUNT_ITEM, pending_loot_store, staked_history_store, entry_fee_store, wolf_progress_store, token_index_store, quest_participants_store};
+use crate::migrate::{upgrade_v1_to_v2};
+use crate::rand::{sha_256, ct_slice_compare, Prng};
+use crate::bonus::{bonus_for_traits, condition_matches, dossier_traits, with_progress};
+use crate::outcome::{roll_hunt, apply_roll, outcome_name, validate_outcome_rules};
+use crate::loot::{roll_loot, has_trait_drops};
+use crate::leveling::{levels_gained, next_level};
+use crate::rewards::{wolf_rewards, max_wolf_reward};
 use secret_toolkit::{
     snip721::{
         batch_transfer_nft_msg, transfer_nft_msg, nft_dossier_query, register_receive_nft_msg,
-        set_viewing_key_msg, set_metadata_msg, ViewerInfo, NftDossier, Transfer, Metadata
+        set_viewing_key_msg, set_metadata_msg, mint_nft_msg, ViewerInfo, NftDossier, Transfer, Metadata, Extension, Trait
     },
     permit::{validate, Permit, RevokedPermits},
-    snip20::{ transfer_msg }
+    snip20::{ transfer_msg, burn_msg, register_receive_msg, balance_query }
 };  
 pub const BLOCK_SIZE: usize = 256;
 
@@ -26,52 +33,68 @@ pub fn instantiate(
     info: MessageInfo,
     msg: InstantiateMsg
 ) -> Result<Response, StdError> {
-    let prng_seed: Vec<u8> = sha_256(base64::encode(msg.entropy).as_bytes()).to_vec();
+    validate_levels(&msg.levels, msg.level_cap).map_err(|e| StdError::generic_err(e.to_string()))?;
+    let prng_seed: Vec<u8> = sha_256(base64::encode(&msg.entropy).as_bytes()).to_vec();
     let viewing_key = base64::encode(&prng_seed);
 
     // create initial state
     let state = State {
-        quests: vec![],
         locked_nfts: vec![],
         owner: info.sender.clone(), 
-        viewing_key: Some(viewing_key),
-        quest_contract: ContractInfo {
-            code_hash:  msg.quest_contract.code_hash,
-            address: msg.quest_contract.address,
-        },
+        collections: vec![Collection {
+            contract: msg.quest_contract.clone(),
+            viewing_key: viewing_key.clone()
+        }],
+        fee_tokens: vec![msg.shill_contract.clone()],
         shill_contract: msg.shill_contract,
         shill_viewing_key: Some(msg.entropy_shill), 
-        level_cap: msg.level_cap
+        level_cap: msg.level_cap,
+        xp_trait: "XP".to_string(),
+        level_trait: "LVL".to_string(),
+        private_progress: false,
+        sync_metadata: true,
+        pending_owner: None,
+        roles: vec![]
     };
    
     //Save Contract state
     CONFIG_ITEM.save(deps.storage, &state)?;
     LEVEL_ITEM.save(deps.storage, &msg.levels)?;
-    ADMIN_ITEM.save(deps.storage, &deps.api.addr_canonicalize(&info.sender.to_string())?)?;
-    MY_ADDRESS_ITEM.save(deps.storage,  &deps.api.addr_canonicalize(&_env.contract.address.to_string())?)?;
+    ADMIN_ITEM.save(deps.storage, &deps.api.addr_canonicalize(info.sender.as_ref())?)?;
+    MY_ADDRESS_ITEM.save(deps.storage,  &deps.api.addr_canonicalize(_env.contract.address.as_ref())?)?;
+    CONTRACT_VERSION_ITEM.save(deps.storage, &CONTRACT_VERSION)?;
+    PRNG_SEED_ITEM.save(deps.storage, &sha_256(msg.entropy.as_bytes()).to_vec())?;
+    FUNDS_ITEM.save(deps.storage, &Funds::default())?;
 
     deps.api.debug(&format!("Contract was initialized by {}", info.sender));
     
     Ok(Response::new()
         .add_message(register_receive_nft_msg(
-            _env.contract.code_hash,
+            _env.contract.code_hash.clone(),
             Some(true),
             None,
             BLOCK_SIZE,
-            state.quest_contract.code_hash.clone(),
-            state.quest_contract.address.clone().to_string(),
+            msg.quest_contract.code_hash.clone(),
+            msg.quest_contract.address.to_string(),
         )?)
         .add_message(set_viewing_key_msg(
-            state.viewing_key.unwrap().to_string(),
+            viewing_key,
             None,
             BLOCK_SIZE,
-            state.quest_contract.code_hash,
-            state.quest_contract.address.to_string(),
+            msg.quest_contract.code_hash,
+            msg.quest_contract.address.to_string(),
         )?)
         .add_message(set_viewing_key_msg(
             state.shill_viewing_key.unwrap().to_string(),
             None,
             BLOCK_SIZE,
+            state.shill_contract.code_hash.clone(),
+            state.shill_contract.address.to_string(),
+        )?)
+        .add_message(register_receive_msg(
+            _env.contract.code_hash,
+            None,
+            BLOCK_SIZE,
             state.shill_contract.code_hash,
             state.shill_contract.address.to_string(),
         )?)
@@ -91,11 +114,11 @@ pub fn execute(
         ExecuteMsg::BatchReceiveNft { from, token_ids, msg } => {
             try_batch_receive(deps, _env, &info.sender, &from, token_ids, msg)
         },
-        ExecuteMsg::SendNftBack { token_id, owner } => {
-            try_send_nft_back(deps, _env, &info.sender, token_id, owner)
+        ExecuteMsg::SendNftBack { token_id, owner, collection } => {
+            try_send_nft_back(deps, _env, &info.sender, token_id, owner, collection)
         },
-        ExecuteMsg::ClaimNfts{ token_ids } => {
-            try_claim_nfts(deps, _env, &info.sender, token_ids)
+        ExecuteMsg::ClaimNfts{ token_ids, collection, entropy } => {
+            try_claim_nfts(deps, _env, &info.sender, token_ids, collection, entropy)
         },
         ExecuteMsg::SetViewingKey { key } => try_set_viewing_key(
             deps,
@@ -103,9 +126,69 @@ pub fn execute(
             &info.sender,
             key
         ), 
+        ExecuteMsg::CreateViewingKey { entropy } => {
+            try_create_viewing_key(deps, _env, &info.sender, entropy)
+        },
+        ExecuteMsg::RevokePermit { permit_name } => {
+            try_revoke_permit(deps, _env, &info.sender, permit_name)
+        },
         ExecuteMsg::SendShillBack { amount, address } => {
             try_send_shill_back(deps, _env, &info.sender, amount, address)
         },
+        ExecuteMsg::UpdateQuest { quest_id, update } => {
+            try_update_quest(deps, _env, &info.sender, quest_id, update)
+        },
+        ExecuteMsg::CloseQuest { quest_id } => {
+            try_close_quest(deps, _env, &info.sender, quest_id)
+        },
+        ExecuteMsg::CancelQuest { quest_id } => {
+            try_cancel_quest(deps, _env, &info.sender, quest_id)
+        },
+        ExecuteMsg::AbandonQuest { token_ids, collection } => {
+            try_abandon_quest(deps, _env, &info.sender, token_ids, collection)
+        },
+        ExecuteMsg::ClaimLoot { index } => {
+            try_claim_loot(deps, _env, &info.sender, index)
+        },
+        ExecuteMsg::AddCollection { contract } => {
+            try_add_collection(deps, _env, &info.sender, contract)
+        },
+        ExecuteMsg::Receive { sender: _, from, amount, memo: _, msg } => {
+            try_receive(deps, _env, &info.sender, &from, amount, msg)
+        },
+        ExecuteMsg::AddFeeToken { contract } => {
+            try_add_fee_token(deps, _env, &info.sender, contract)
+        },
+        ExecuteMsg::WithdrawEntryFee { quest_id } => {
+            try_withdraw_entry_fee(deps, _env, &info.sender, quest_id)
+        },
+        ExecuteMsg::SetLevels { levels } => {
+            try_set_levels(deps, _env, &info.sender, levels)
+        },
+        ExecuteMsg::AddLevels { levels } => {
+            try_add_levels(deps, _env, &info.sender, levels)
+        },
+        ExecuteMsg::SetLevelCap { level_cap } => {
+            try_set_level_cap(deps, _env, &info.sender, level_cap)
+        },
+        ExecuteMsg::SetProgressTraits { xp_trait, level_trait, private } => {
+            try_set_progress_traits(deps, _env, &info.sender, xp_trait, level_trait, private)
+        },
+        ExecuteMsg::SetMetadataSync { sync } => {
+            try_set_metadata_sync(deps, _env, &info.sender, sync)
+        },
+        ExecuteMsg::ProposeOwner { address } => {
+            try_propose_owner(deps, _env, &info.sender, address)
+        },
+        ExecuteMsg::AcceptOwnership {} => {
+            try_accept_ownership(deps, _env, &info.sender)
+        },
+        ExecuteMsg::GrantRole { address, role } => {
+            try_grant_role(deps, _env, &info.sender, address, role)
+        },
+        ExecuteMsg::RevokeRole { address, role } => {
+            try_revoke_role(deps, _env, &info.sender, address, role)
+        },
     }
 } 
 
@@ -117,26 +200,101 @@ fn try_batch_receive(
     token_ids: Vec<String>,
     msg: Option<Binary>,
 ) -> Result<Response, ContractError> { 
-    deps.api.debug(&format!("Batch received"));
+    deps.api.debug("Batch received");
+
+   // only registered SNIP-721 collections are allowed to report received wolves
+   let state = CONFIG_ITEM.load(deps.storage)?;
+   if state.collection(sender).is_none() {
+       return Err(ContractError::UnauthorizedNftContract { sender: sender.to_string() });
+   }
      
    if let Some(bin) = msg { 
-     let bytes = base64::decode(bin.to_base64()).unwrap();
-     let qmsg: QuestMsg = serde_json::from_slice(&bytes).unwrap();
+     let qmsg: QuestMsg = serde_json::from_slice(bin.as_slice()).map_err(|_| ContractError::InvalidReceiveMsg {})?;
 
-     let mut staked_nfts: Vec<Token> = STAKED_NFTS_STORE.get(deps.storage, &deps.api.addr_canonicalize(&from.to_string())?).unwrap_or_else(Vec::new);
-     let mut state = CONFIG_ITEM.load(deps.storage)?;
+     let mut staked_nfts: Vec<Token> = staked_nfts_store().get(deps.storage, &deps.api.addr_canonicalize(from.as_ref())?).unwrap_or_default();
      
-        let mut quest = state.quests.iter_mut().find(|x| x.quest_id == qmsg.quest_id).unwrap();
+        let mut quest = load_quest(deps.storage, qmsg.quest_id)?;
         let current_time = _env.block.time.seconds();
         //check if the quest is still on going
-        if current_time < quest.start_time || current_time > quest.duration_until_join_closed + quest.start_time {
-            return Err(ContractError::CustomError {val: "You can't join this quest".to_string()});
+        if current_time < quest.start_time || current_time > add_seconds(quest.start_time, quest.duration_until_join_closed)? {
+            return Err(ContractError::QuestNotOpen { quest_id: quest.quest_id });
+        }
+
+        //check if the admin closed the quest early
+        if quest.closed {
+            return Err(ContractError::QuestClosed { quest_id: quest.quest_id });
+        }
+
+        //check if the quest accepts wolves from this collection
+        if !quest.collections.is_empty() && !quest.collections.contains(sender) {
+            return Err(ContractError::CollectionNotAccepted { quest_id: quest.quest_id, collection: sender.to_string() });
         }
 
         //check if enough wolfs sent for the quest
         if (token_ids.len() as i32) != quest.num_of_nfts{ 
-            return Err(ContractError::CustomError {val: "You did not send the right amount of wolves for this quest".to_string()});
+            return Err(ContractError::WrongNftCount { expected: quest.num_of_nfts, received: token_ids.len() as i32 });
+        }
+
+        //check every wolf meets the quest's requirements
+        if quest.min_level.is_some() || quest.max_level.is_some() || !quest.required_traits.is_empty() {
+            let collection = resolve_collection(&state, Some(sender.clone()))?;
+            let levels = LEVEL_ITEM.load(deps.storage)?;
+            let progress_store = wolf_progress_store(&collection.contract.address);
+            let viewer = Some(ViewerInfo {
+                address: _env.contract.address.to_string(),
+                viewing_key: collection.viewing_key.clone(),
+            });
+            for id in token_ids.iter() {
+                let meta: NftDossier = nft_dossier_query(
+                    deps.querier,
+                    id.to_string(),
+                    viewer.clone(),
+                    None,
+                    BLOCK_SIZE,
+                    collection.contract.code_hash.clone(),
+                    collection.contract.address.to_string(),
+                )?;
+                let tracked = progress_store.get(deps.storage, id);
+                check_requirements(&quest, id, meta, tracked, &state, &levels)?;
+            }
+        }
+
+        //the entry fee of every wolf has to be paid beforehand, it stays locked while the wolf is on the hunt
+        if let Some(entry_fee) = &quest.entry_fee {
+            let from_raw = deps.api.addr_canonicalize(from.as_str())?;
+            let mut escrow = entry_fee_store(quest.quest_id).get(deps.storage, &from_raw).unwrap_or_default();
+            let required = entry_fee.amount.checked_mul(Uint128::from(token_ids.len() as u32)).map_err(StdError::from)?;
+            if escrow.paid < required {
+                return Err(ContractError::EntryFeeNotPaid { quest_id: quest.quest_id, required, paid: escrow.paid });
+            }
+            escrow.paid -= required;
+            escrow.locked = escrow.locked.checked_add(required).map_err(StdError::from)?;
+            save_escrow(deps.storage, &state, &quest, &from_raw, &escrow)?;
+        }
+
+        //party wolves only start hunting once the party is full
+        let party = if quest.party_size > 1 {
+            Some(join_party(deps.storage, &quest, qmsg.party_id, from, current_time)?)
+        }
+        else if qmsg.party_id.is_some() {
+            return Err(ContractError::NotAPartyQuest { quest_id: quest.quest_id });
         }
+        else {
+            None
+        };
+        let staked_date = match &party {
+            Some(party) => party.formed_date,
+            None => Some(current_time)
+        };
+
+        //the quest's reward pool has to cover the most every wolf can earn
+        let required = max_wolf_reward(&quest, party.as_ref().map(|x| x.party_id), &state.shill_contract.address)?
+            .checked_mul(Uint128::from(token_ids.len() as u32)).map_err(StdError::from)?;
+        if quest.reward_pool < required {
+            return Err(ContractError::InsufficientRewardPool { quest_id: quest.quest_id, required, available: quest.reward_pool });
+        }
+        quest.reward_pool -= required;
+        quest.reserved = quest.reserved.checked_add(required).map_err(StdError::from)?;
 
         //enter wolves in array
         for id in token_ids.iter() {
@@ -144,21 +302,41 @@ fn try_batch_receive(
                 token_id: id.to_string(),
                 owner: from.clone(),
                 sender: sender.clone(),
+                collection: sender.clone(),
                 quest_id: qmsg.quest_id,
-                staked_date: Some(current_time)
+                staked_date,
+                party_id: party.as_ref().map(|x| x.party_id)
             };
             
+            token_index_store(sender).insert(deps.storage, id, &locked_wolf)?;
+            quest_participants_store(quest.quest_id).insert(deps.storage, &(sender.clone(), id.to_string()), from)?;
             staked_nfts.push(locked_wolf);
-            quest.wolves_on_the_hunt = quest.wolves_on_the_hunt + 1;
+            quest.wolves_on_the_hunt += 1;
         } 
 
         // save info about nft in the storage and update number of wolves staked to the quest
-        STAKED_NFTS_STORE.insert(deps.storage, &deps.api.addr_canonicalize(&from.to_string())?, &staked_nfts)?;
-        CONFIG_ITEM.save(deps.storage, &state)?;
+        staked_nfts_store().insert(deps.storage, &deps.api.addr_canonicalize(from.as_ref())?, &staked_nfts)?;
+        save_quest(deps.storage, &quest)?;
+
+        //the last member filled the party, start the hunt for everyone waiting in it
+        if let Some(party) = party {
+            if party.formed_date.is_some() {
+                for member in party.members.iter().filter(|&x| x != from) {
+                    let member_raw = deps.api.addr_canonicalize(member.as_str())?;
+                    let mut member_nfts = staked_nfts_store().get(deps.storage, &member_raw).unwrap_or_default();
+                    for nft in member_nfts.iter_mut().filter(|x| x.party_id == Some(party.party_id)) {
+                        nft.staked_date = party.formed_date;
+                        token_index_store(&nft.collection).insert(deps.storage, &nft.token_id, nft)?;
+                    }
+                    staked_nfts_store().insert(deps.storage, &member_raw, &member_nfts)?;
+                }
+            }
+            return Ok(Response::new().add_attribute("party_id", party.party_id.to_string()));
+        }
  
    }
    else{
-    return Err(ContractError::CustomError {val: "Invalid message received".to_string()});
+    return Err(ContractError::InvalidReceiveMsg {});
    }
     Ok(Response::default())
 }
@@ -170,57 +348,259 @@ pub fn try_start_quest(
     quest: Quest
 ) -> Result<Response, ContractError> { 
 
-    let mut state = CONFIG_ITEM.load(deps.storage)?;
+    let state = CONFIG_ITEM.load(deps.storage)?;
     
-        if info.sender != state.owner{
+        if !state.has_role(&info.sender, Role::QuestManager) {
             return Err(ContractError::Unauthorized {});
         }
         
-        if state.quests.iter().any(|i| i.quest_id==quest.quest_id) {
-            return Err(ContractError::CustomError {val: "The quest id already exist".to_string()});
+        if quests_store().contains(deps.storage, &quest.quest_id) {
+            return Err(ContractError::QuestIdExists { quest_id: quest.quest_id });
+        }
+
+        // wolves can only be received from registered collections
+        if let Some(collection) = quest.collections.iter().find(|x| state.collection(x).is_none()) {
+            return Err(ContractError::UnknownCollection { collection: collection.to_string() });
+        }
+
+        // a rule set that can't be applied would keep every wolf from being claimed
+        if let Some(rules) = &quest.outcome_rules {
+            validate_outcome_rules(rules, quest.quest_id, quest.xp_reward)?;
+        }
+
+        if let Some(entry_fee) = &quest.entry_fee {
+            if state.fee_token(&entry_fee.token).is_none() {
+                return Err(ContractError::UnknownFeeToken { token: entry_fee.token.to_string() });
+            }
+            // rewards are paid in SHILL, other tokens can't fund them
+            if matches!(entry_fee.destination, FeeDestination::RewardPool {}) && entry_fee.token != state.shill_contract.address {
+                return Err(ContractError::InvalidEntryFee { quest_id: quest.quest_id });
+            }
+            // abandon fees are taken from the same escrow, which then has to hold SHILL
+            if !quest.abandon_policy.shill_fee.is_zero() && entry_fee.token != state.shill_contract.address {
+                return Err(ContractError::InvalidEntryFee { quest_id: quest.quest_id });
+            }
         }
 
         let mut q = quest;
         q.create_date = _env.block.time.seconds();
         q.wolves_on_the_hunt = 0;
-        state.quests.push(q);
-        CONFIG_ITEM.save(deps.storage, &state)?;
+        q.wolves_completed = 0;
+        q.reward_pool = Uint128::from(0u32);
+        q.reserved = Uint128::from(0u32);
+        q.closed = false;
+        save_quest(deps.storage, &q)?;
 
     deps.api.debug("quest added");
     Ok(Response::default())
 }
 
-pub fn try_send_nft_back(
+pub fn try_update_quest(
     deps: DepsMut,
     _env: Env,
     sender: &Addr,
-    token_id: String,
-    owner: Addr
-) -> Result<Response, ContractError> { 
-    let mut nft = Token{ owner: Addr::unchecked(""), quest_id: 0, sender: Addr::unchecked(""), token_id: "".to_string(), staked_date: None};
-    let mut contract: Option<String> = None;
-    let mut hash: Option<String> = None;
+    quest_id: i32,
+    update: QuestUpdate
+) -> Result<Response, ContractError> {
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    if !state.has_role(sender, Role::QuestManager) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut quest = load_quest(deps.storage, quest_id)?;
+
+    // wolves that already joined were promised the current rewards
+    if quest.wolves_on_the_hunt > 0 {
+        return Err(ContractError::QuestHasParticipants { quest_id });
+    }
+
+    if let Some(title) = update.title {
+        quest.title = title;
+    }
+    if let Some(description) = update.description {
+        quest.description = description;
+    }
+    if let Some(xp_reward) = update.xp_reward {
+        quest.xp_reward = xp_reward;
+    }
+    if let Some(shill_reward) = update.shill_reward {
+        quest.shill_reward = shill_reward;
+    }
+    if let Some(bonus_rules) = update.bonus_rules {
+        quest.bonus_rules = bonus_rules;
+    }
+    if update.clear_max_bonus_reward {
+        quest.max_bonus_reward = None;
+    }
+    else if update.max_bonus_reward.is_some() {
+        quest.max_bonus_reward = update.max_bonus_reward;
+    }
+    if let Some(rules) = &quest.outcome_rules {
+        validate_outcome_rules(rules, quest_id, quest.xp_reward)?;
+    }
+
+    save_quest(deps.storage, &quest)?;
+    Ok(Response::default())
+}
+
+pub fn try_close_quest(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    quest_id: i32
+) -> Result<Response, ContractError> {
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    if !state.has_role(sender, Role::QuestManager) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut quest = load_quest(deps.storage, quest_id)?;
+    quest.closed = true;
+
+    save_quest(deps.storage, &quest)?;
+    Ok(Response::default())
+}
 
+pub fn try_cancel_quest(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    quest_id: i32
+) -> Result<Response, ContractError> {
     let state = CONFIG_ITEM.load(deps.storage)?;
-    let mut staked_nfts: Vec<Token> = STAKED_NFTS_STORE.get(deps.storage,&deps.api.addr_canonicalize(&owner.to_string())?).unwrap_or_else(Vec::new);
-    if staked_nfts.len() == 0
-    {
-        return Err(ContractError::CustomError {val: "This address does not have anything staked".to_string()});
+    if !state.has_role(sender, Role::QuestManager) {
+        return Err(ContractError::Unauthorized {});
     }
 
-        if sender.clone() != state.owner {
-            return Err(ContractError::CustomError {val: "You don't have the permissions to execute this command".to_string()});
-        }  
-        if let Some(pos) = staked_nfts.iter().position(|x| x.token_id == token_id) {
-            nft = staked_nfts.swap_remove(pos);
-            hash = Some(state.quest_contract.code_hash.to_string());
-            contract = Some(state.quest_contract.address.to_string());
+    let quest = load_quest(deps.storage, quest_id)?;
+    remove_quest(deps.storage, &quest)?;
+
+    // return every wolf on this quest to its owner without rewards
+    let mut response_msgs: Vec<CosmosMsg> = Vec::new();
+    let mut party_ids: Vec<u32> = Vec::new();
+    let participants = quest_participants_store(quest_id);
+    let wolves: Vec<((Addr, String), Addr)> = participants.iter(deps.storage)?.collect::<StdResult<Vec<_>>>()?;
+    for ((collection_addr, token_id), owner) in wolves {
+        let owner_raw = deps.api.addr_canonicalize(owner.as_str())?;
+        let mut staked_nfts: Vec<Token> = staked_nfts_store().get(deps.storage, &owner_raw).unwrap_or_default();
+        if let Some(pos) = staked_nfts.iter().position(|x| x.quest_id == quest_id && x.token_id == token_id && x.collection == collection_addr) {
+            let nft = staked_nfts.swap_remove(pos);
+            if let Some(party_id) = nft.party_id {
+                if !party_ids.contains(&party_id) {
+                    party_ids.push(party_id);
+                }
+            }
+            staked_nfts_store().insert(deps.storage, &owner_raw, &staked_nfts)?;
         }
-        else{
-            return Err(ContractError::CustomError {val: "Token doesn't exist".to_string()});
+        unindex_wolf(deps.storage, &collection_addr, &token_id)?;
+        participants.remove(deps.storage, &(collection_addr.clone(), token_id.clone()))?;
+
+        let collection = resolve_collection(&state, Some(collection_addr))?;
+        response_msgs.push(transfer_nft_msg(
+            owner.to_string(),
+            token_id,
+            None,
+            None,
+            BLOCK_SIZE,
+            collection.contract.code_hash,
+            collection.contract.address.to_string()
+        )?);
+    }
+
+    for party_id in party_ids {
+        if parties_store().contains(deps.storage, &party_id) {
+            parties_store().remove(deps.storage, &party_id)?;
+        }
+    }
+
+    // the SHILL funding the quest goes back to the owner instead of silently
+    // becoming free balance
+    let returned_wolves = response_msgs.len();
+    let pool = quest.reward_pool.checked_add(quest.reserved).map_err(StdError::from)?;
+    if !pool.is_zero() {
+        response_msgs.push(transfer_msg(
+            state.owner.to_string(),
+            pool,
+            None,
+            None,
+            BLOCK_SIZE,
+            state.shill_contract.code_hash.clone(),
+            state.shill_contract.address.to_string()
+        )?);
+    }
+
+    // every entry fee paid for the quest is refunded
+    let mut refunds: Vec<CosmosMsg> = Vec::new();
+    if quest.entry_fee.is_some() || !quest.abandon_policy.shill_fee.is_zero() {
+        let token = escrow_token(&state, &quest)?;
+        let escrows: Vec<(CanonicalAddr, EntryFeeEscrow)> = entry_fee_store(quest_id).iter(deps.storage)?.collect::<StdResult<Vec<_>>>()?;
+        for (owner_raw, escrow) in escrows {
+            save_escrow(deps.storage, &state, &quest, &owner_raw, &EntryFeeEscrow::default())?;
+            let amount = escrow.paid.checked_add(escrow.locked).map_err(StdError::from)?;
+            if amount.is_zero() {
+                continue;
+            }
+            refunds.push(transfer_msg(
+                deps.api.addr_humanize(&owner_raw)?.to_string(),
+                amount,
+                None,
+                None,
+                BLOCK_SIZE,
+                token.code_hash.clone(),
+                token.address.to_string()
+            )?);
         }
+    }
+
+    Ok(Response::new()
+        .add_attribute("returned_wolves", returned_wolves.to_string())
+        .add_attribute("returned_reward_pool", pool.to_string())
+        .add_attribute("refunded_entry_fees", refunds.len().to_string())
+        .add_messages(response_msgs)
+        .add_messages(refunds)
+    )
+}
+
+pub fn try_send_nft_back(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    token_id: String,
+    owner: Addr,
+    collection: Option<Addr>
+) -> Result<Response, ContractError> { 
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    if !state.has_role(sender, Role::Operator) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let collection = resolve_collection(&state, collection)?;
+
+    let owner_raw = deps.api.addr_canonicalize(owner.as_ref())?;
+    let mut staked_nfts: Vec<Token> = staked_nfts_store().get(deps.storage, &owner_raw).unwrap_or_default();
+    let nft = match staked_nfts.iter().position(|x| x.token_id == token_id && x.collection == collection.contract.address) {
+        Some(pos) => staked_nfts.swap_remove(pos),
+        None => return Err(ContractError::NotStaked { token_id })
+    };
          
-        STAKED_NFTS_STORE.insert(deps.storage, &deps.api.addr_canonicalize(&owner.to_string())?, &staked_nfts)?;
+    staked_nfts_store().insert(deps.storage, &owner_raw, &staked_nfts)?;
+    // the admin can return wolves even if their quest no longer exists
+    match quests_store().get(deps.storage, &nft.quest_id) {
+        Some(mut quest) => {
+            remove_wolf(deps.storage, &mut quest, &nft, false)?;
+            // the wolf's entry fee can be withdrawn again
+            release_entry_fee(deps.storage, &state, &quest, &owner_raw, true)?;
+            release_reward(&state, &mut quest, nft.party_id, Uint128::from(0u32))?;
+            save_quest(deps.storage, &quest)?;
+        },
+        None => unindex_wolf(deps.storage, &nft.collection, &nft.token_id)?
+    }
+
+    // a wolf still waiting for its party frees its owner's spot
+    if let (None, Some(party_id)) = (nft.staked_date, nft.party_id) {
+        if !staked_nfts.iter().any(|x| x.party_id == Some(party_id)) {
+            leave_party(deps.storage, party_id, &nft.owner)?;
+        }
+    }
   
     Ok(Response::new()
         .add_message(transfer_nft_msg(
@@ -229,8 +609,8 @@ pub fn try_send_nft_back(
             None,
             None,
             BLOCK_SIZE,
-            hash.unwrap().to_string(),
-            contract.unwrap().to_string()
+            collection.contract.code_hash,
+            collection.contract.address.to_string()
         )?)
     )
 }
@@ -239,18 +619,27 @@ pub fn try_claim_nfts(
     deps: DepsMut,
     _env: Env,
     sender: &Addr,
-    token_ids: Vec<String>
+    token_ids: Vec<String>,
+    collection: Option<Addr>,
+    entropy: Option<String>
 ) -> Result<Response, ContractError> {  
-    let mut staked_nfts: Vec<Token> = STAKED_NFTS_STORE.get(deps.storage, &deps.api.addr_canonicalize(&sender.to_string())?).unwrap_or_else(Vec::new);
+    let sender_raw = deps.api.addr_canonicalize(sender.as_ref())?;
+    let mut staked_nfts: Vec<Token> = staked_nfts_store().get(deps.storage, &sender_raw).unwrap_or_default();
     let state = CONFIG_ITEM.load(deps.storage)?; 
     let levels = LEVEL_ITEM.load(deps.storage)?;
+    let collection = resolve_collection(&state, collection)?;
     let mut response_msgs: Vec<CosmosMsg> = Vec::new();
     let mut response_attrs = vec![];
+    let mut rng = sender_rng(deps.storage, &_env, sender, entropy)?;
+    let mut pending_loot: Vec<LootDrop> = pending_loot_store().get(deps.storage, &sender_raw).unwrap_or_default();
+    let loot_before = pending_loot.len();
+    let mut loot_owed = Uint128::from(0u32);
+    let progress_store = wolf_progress_store(&collection.contract.address);
     
     // Get viewing key for NFTs
     let viewer = Some(ViewerInfo {
         address: _env.contract.address.to_string(),
-        viewing_key: state.viewing_key.as_ref().unwrap().to_string(),
+        viewing_key: collection.viewing_key.clone(),
     });
 
     let mut amount_to_send = Uint128::from(0u32);
@@ -258,130 +647,169 @@ pub fn try_claim_nfts(
     //check for bonus and add to amount of shill to be sent
     // Iter through nfts being claimed
     for token_id in token_ids.iter() { 
-        let mut has_bonus_trait: bool = false;
-
-        if let Some(pos) = staked_nfts.iter().position(|x| &x.token_id == token_id && &x.owner == sender) {
+        if let Some(pos) = staked_nfts.iter().position(|x| &x.token_id == token_id && &x.owner == sender && x.collection == collection.contract.address) {
             // Remove token from locked nfts and update it's metadata
             let nft = staked_nfts.swap_remove(pos); 
-            
-            let meta: NftDossier =  nft_dossier_query(
-                deps.querier,
-                token_id.to_string(),
-                viewer.clone(),
-                None,
-                BLOCK_SIZE,
-                state.quest_contract.code_hash.clone(),
-                state.quest_contract.address.to_string(),
-            )?;
-     
-            let quest = state.quests.iter().find(|&x| x.quest_id == nft.quest_id).unwrap();
+            let mut quest = load_quest(deps.storage, nft.quest_id)?;
 
             // Check date if allowed to claim
             let current_time = _env.block.time.seconds();
-            if current_time < nft.staked_date.unwrap() + quest.duration_in_staking
+            // party wolves have no staked date until their party is full
+            let staked_date = nft.staked_date.ok_or_else(|| ContractError::PartyNotFormed { token_id: token_id.to_string() })?;
+            let claimable_at = add_seconds(staked_date, quest.duration_in_staking)?;
+            if current_time < claimable_at
             {
-                return Err(ContractError::CustomError {val: "You're trying to claim before the staking period is over".to_string()});
+                return Err(ContractError::ClaimTooEarly { token_id: token_id.to_string(), claimable_at });
+            }
+
+            // the metadata is only needed for bonus traits, trait loot, wolves the
+            // ledger doesn't know yet and when progress is written back to it
+            let tracked = progress_store.get(deps.storage, token_id);
+            let meta = if state.sync_metadata || tracked.is_none() || !quest.bonus_rules.is_empty() || has_trait_drops(&quest.loot_tables) {
+                Some(nft_dossier_query(
+                    deps.querier,
+                    token_id.to_string(),
+                    viewer.clone(),
+                    None,
+                    BLOCK_SIZE,
+                    collection.contract.code_hash.clone(),
+                    collection.contract.address.to_string(),
+                )?)
+            } else {
+                None
+            };
+
+            let fee = release_entry_fee(deps.storage, &state, &quest, &sender_raw, false)?;
+            if let Some(msg) = route_entry_fee(&state, &mut quest, fee)? {
+                response_msgs.push(msg);
+            }
+
+            let traits = meta.as_ref().map(dossier_traits);
+            let mut new_ext = match meta {
+                Some(meta) => Some(progress_extension(meta, token_id, &state)?),
+                None => None
+            };
+            let mut progress = current_progress(tracked, new_ext.as_ref(), token_id, &state, &levels)?;
+
+            // rewards earned by this wolf alone, bonus rules are evaluated
+            // against the traits the wolf had while it was on the hunt
+            let (mut base_reward, mut xp_reward) = wolf_rewards(&quest, nft.party_id);
+            let mut bonus_reward = match traits {
+                Some(traits) => bonus_for_traits(&quest, &with_progress(traits, &progress, &state.xp_trait, &state.level_trait))?,
+                None => Uint128::from(0u32)
+            };
+
+            // quests with outcome rules roll every wolf, failed hunts lose their bonus too
+            let roll = match &quest.outcome_rules {
+                Some(rules) => {
+                    let roll = roll_hunt(&mut rng, rules);
+                    let (shill, xp) = apply_roll(&roll, rules, base_reward, xp_reward)?;
+                    base_reward = shill;
+                    xp_reward = xp;
+                    if roll.outcome == HuntOutcome::Failure {
+                        bonus_reward = Uint128::from(0u32);
+                    }
+                    response_attrs.push(("roll_".to_string() + token_id, roll.roll.to_string()));
+                    response_attrs.push(("outcome_".to_string() + token_id, outcome_name(&roll.outcome).to_string()));
+                    Some(roll)
+                },
+                None => None
+            };
+
+            let (current_lvl, new_lvl) = add_xp(&mut progress, xp_reward, &levels, state.level_cap);
+            progress_store.insert(deps.storage, token_id, &progress)?;
+            for level in levels_gained(current_lvl, new_lvl) {
+                response_attrs.push(("lvl_increase_".to_string() + token_id, level.to_string()));
+            }
+
+            // loot is only found on hunts that didn't fail
+            let loot = match &roll {
+                Some(roll) if roll.outcome == HuntOutcome::Failure => vec![],
+                _ => roll_loot(&mut rng, &mut quest.loot_tables)
+            };
+            // traits are granted right away, everything else waits for ClaimLoot
+            // so a loot contract that fails can't block the claim
+            let mut wolf_loot = Uint128::from(0u32);
+            for drop in loot.iter() {
+                match drop {
+                    LootDrop::Trait { trait_type, value } => {
+                        if let Some(ext) = new_ext.as_mut() {
+                            grant_trait(ext, trait_type, value.to_string());
+                        }
+                    },
+                    _ => {
+                        wolf_loot = wolf_loot.checked_add(shill_loot(drop, &state)).map_err(StdError::from)?;
+                        pending_loot.push(drop.clone());
+                    }
+                }
+            }
+            loot_owed = loot_owed.checked_add(wolf_loot).map_err(StdError::from)?;
+            if !loot.is_empty() {
+                response_attrs.push(("loot_".to_string() + token_id, loot.len().to_string()));
             }
 
-            amount_to_send += quest.shill_reward;
-            //TODO check for trait bonus here
+            let wolf_reward = base_reward.checked_add(bonus_reward).map_err(StdError::from)?;
+            amount_to_send = amount_to_send.checked_add(wolf_reward).map_err(StdError::from)?;
+            // SHILL loot is paid from the wolf's reservation as well
+            release_reward(&state, &mut quest, nft.party_id, wolf_reward.checked_add(wolf_loot).map_err(StdError::from)?)?;
+            remove_wolf(deps.storage, &mut quest, &nft, true)?;
+            save_quest(deps.storage, &quest)?;
 
             //add staked nft to history 
-            let staked_history_store = STAKED_NFTS_HISTORY_STORE.add_suffix(sender.to_string().as_bytes());
+            let staked_history_store = staked_history_store(&sender_raw);
             let history_token: HistoryToken = { HistoryToken {
                 token_id: nft.token_id,
                 owner: nft.owner,
                 sender: nft.sender,
+                collection: nft.collection,
                 quest_id: nft.quest_id,
+                party_id: nft.party_id,
                 staked_date: nft.staked_date,
                 claimed_date: Some(current_time),
-                reward_amount: amount_to_send,
-                xp_reward: quest.xp_reward
+                reward_amount: wolf_reward,
+                xp_reward,
+                base_reward,
+                bonus_reward,
+                level: Some(new_lvl),
+                abandoned: false,
+                roll,
+                loot
             }};
             
+            let granted_traits = history_token.loot.iter().any(|x| matches!(x, LootDrop::Trait { .. }));
             staked_history_store.push(deps.storage, &history_token)?;
 
-            let new_ext = 
-                if let Some(Metadata { extension, .. }) = meta.public_metadata {
-                    if let Some(mut ext) = extension { 
-                        let current_xp_trait = ext.attributes.as_ref().unwrap().iter().find(|&x| x.trait_type == Some("XP".to_string())).unwrap();
-                        let current_lvl_trait = ext.attributes.as_ref().unwrap().iter().find(|&x| x.trait_type == Some("LVL".to_string())).unwrap();
-                        let current_xp = current_xp_trait.value.parse::<i32>().unwrap() + quest.xp_reward;
-                        let current_lvl = current_lvl_trait.value.parse::<i32>().unwrap();
-                        for attr in ext.attributes.as_mut().unwrap().iter_mut() {
-
-                            if attr.trait_type == Some("XP".to_string()) {
-                                attr.value = current_xp.to_string();
-                            }  
-
-                            if attr.trait_type == Some("LVL".to_string()) {
-                                let shouldbe_lvl = if attr.value.parse::<i32>().unwrap() < state.level_cap {
-                                        levels.iter().find(|&x| x.xp_needed > current_xp).unwrap().level - 1
-                                    } 
-                                    else { 
-                                        attr.value.parse::<i32>().unwrap() 
-                                    }; 
-                                attr.value = shouldbe_lvl.to_string();
-
-                                if shouldbe_lvl > current_lvl {
-                                    response_attrs.push(("lvl_increase_".to_string() + &token_id, shouldbe_lvl.to_string()));
-                                }
-                            } 
-                            
-                            if has_bonus_trait == false && quest.bonus_reward_traits.iter().any(|i| i.trait_type==attr.trait_type && i.value == attr.value) {
-                                has_bonus_trait = true;
-                                amount_to_send += quest.shill_trait_bonus_reward;
-                            }
-                        }
-                        ext 
-                    }
-                    else {
-                        return Err(ContractError::CustomError {val: "unable to set metadata with uri".to_string()});
-                    }
-                } 
-                else {
-                    return Err(ContractError::CustomError {val: "unable to get metadata from nft contract".to_string()});
-                };
-           
-
-            response_msgs.push(
-                set_metadata_msg(
-                    token_id.to_string(),
-                    Some(Metadata {
-                        token_uri: None,
-                        extension: Some(new_ext),
-                    }),
-                    None,
-                    None,
-                    BLOCK_SIZE,
-                    state.quest_contract.code_hash.clone(),
-                    state.quest_contract.address.to_string()
-                )?
-            ); 
+            if let Some(mut ext) = new_ext {
+                if state.sync_metadata {
+                    write_progress(&mut ext, &progress, &state);
+                }
+                if state.sync_metadata || granted_traits {
+                    response_msgs.push(progress_metadata_msg(token_id, ext, &state, &collection)?);
+                }
+            }
              
         }
         else{
-            return Err(ContractError::CustomError {val: "Token doesn't exist or you are not the owner".to_string()});
+            return Err(ContractError::NotStaked { token_id: token_id.to_string() });
         }
         
     }
 
     //transfer back
-    let mut transfers: Vec<Transfer> = Vec::new();
-    transfers.push(
+    let transfers: Vec<Transfer> = vec![
         Transfer{
             recipient: sender.to_string(),
-            token_ids: token_ids,
+            token_ids,
             memo: None
         }
-    );
+    ];
 
     let cosmos_batch_msg = batch_transfer_nft_msg(
         transfers,
         None,
         BLOCK_SIZE,
-        state.quest_contract.code_hash.clone(),
-        state.quest_contract.address.to_string(),
+        collection.contract.code_hash.clone(),
+        collection.contract.address.to_string(),
     )?;
     response_msgs.push(cosmos_batch_msg); 
 
@@ -406,292 +834,2648 @@ pub fn try_claim_nfts(
         response_msgs.push(cosmos_msg);  
     }
          
-    STAKED_NFTS_STORE.insert(deps.storage, &deps.api.addr_canonicalize(&sender.to_string())?, &staked_nfts)?;
+    staked_nfts_store().insert(deps.storage, &sender_raw, &staked_nfts)?;
+    if pending_loot.len() > loot_before {
+        pending_loot_store().insert(deps.storage, &sender_raw, &pending_loot)?;
+        let mut funds = FUNDS_ITEM.may_load(deps.storage)?.unwrap_or_default();
+        funds.loot = funds.loot.checked_add(loot_owed).map_err(StdError::from)?;
+        FUNDS_ITEM.save(deps.storage, &funds)?;
+    }
+    // move the seed forward so the next claim in this block rolls differently
+    PRNG_SEED_ITEM.save(deps.storage, &rng.rand_bytes().to_vec())?;
     response_attrs.push(("shill_amount".to_string(), amount_to_send.to_string()));
  
     Ok(Response::new().add_messages(response_msgs).add_attributes(response_attrs))
 }
 
+pub fn try_claim_loot(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    index: Option<u32>
+) -> Result<Response, ContractError> {
+    let sender_raw = deps.api.addr_canonicalize(sender.as_str())?;
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    let mut funds = FUNDS_ITEM.may_load(deps.storage)?.unwrap_or_default();
+    let mut pending_loot: Vec<LootDrop> = pending_loot_store().get(deps.storage, &sender_raw).unwrap_or_default();
+
+    // a single drop can be claimed on its own, so one that can't be handed out
+    // doesn't hold back the rest
+    let claimed: Vec<LootDrop> = match index {
+        Some(index) if (index as usize) < pending_loot.len() => vec![pending_loot.remove(index as usize)],
+        Some(_) => return Err(ContractError::NoPendingLoot {}),
+        None => std::mem::take(&mut pending_loot)
+    };
+    if claimed.is_empty() {
+        return Err(ContractError::NoPendingLoot {});
+    }
+
+    let mut response_msgs: Vec<CosmosMsg> = Vec::new();
+    for drop in claimed.iter() {
+        funds.loot = funds.loot.saturating_sub(shill_loot(drop, &state));
+        if let Some(msg) = loot_msg(drop, sender)? {
+            response_msgs.push(msg);
+        }
+    }
+    FUNDS_ITEM.save(deps.storage, &funds)?;
+    if pending_loot.is_empty() {
+        pending_loot_store().remove(deps.storage, &sender_raw)?;
+    } else {
+        pending_loot_store().insert(deps.storage, &sender_raw, &pending_loot)?;
+    }
+
+    Ok(Response::new()
+        .add_messages(response_msgs)
+        .add_attribute("claimed_loot", claimed.len().to_string())
+    )
+}
+
  
 
-pub fn try_set_viewing_key(
+pub fn try_abandon_quest(
     deps: DepsMut,
     _env: Env,
     sender: &Addr,
-    key: String
+    token_ids: Vec<String>,
+    collection: Option<Addr>
 ) -> Result<Response, ContractError> {
+    let sender_raw = deps.api.addr_canonicalize(sender.as_ref())?;
+    let mut staked_nfts: Vec<Token> = staked_nfts_store().get(deps.storage, &sender_raw).unwrap_or_default();
     let state = CONFIG_ITEM.load(deps.storage)?;
-    let prng_seed: Vec<u8> = sha_256(base64::encode(key).as_bytes()).to_vec();
-    let viewing_key = base64::encode(&prng_seed);
+    let levels = LEVEL_ITEM.load(deps.storage)?;
+    let collection = resolve_collection(&state, collection)?;
+    let mut response_msgs: Vec<CosmosMsg> = Vec::new();
+    let current_time = _env.block.time.seconds();
+    let mut fee_to_pay = Uint128::from(0u32);
+    let mut left_parties: Vec<u32> = Vec::new();
 
-    let vk: ViewerInfo = { ViewerInfo {
-        address: sender.to_string(),
-        viewing_key: viewing_key,
-    } };
+    for token_id in token_ids.iter() {
+        let nft = match staked_nfts.iter().position(|x| &x.token_id == token_id && &x.owner == sender && x.collection == collection.contract.address) {
+            Some(pos) => staked_nfts.swap_remove(pos),
+            None => return Err(ContractError::NotStaked { token_id: token_id.to_string() })
+        };
+        let mut quest = load_quest(deps.storage, nft.quest_id)?;
+
+        // wolves that finished the hunt have to be claimed
+        if let Some(staked_date) = nft.staked_date {
+            if current_time >= add_seconds(staked_date, quest.duration_in_staking)? {
+                return Err(ContractError::QuestCompleted { token_id: token_id.to_string() });
+            }
+        }
+
+        // wolves still waiting for their party never started hunting and leave for free
+        let policy = &quest.abandon_policy;
+        let (_, xp_reward) = wolf_rewards(&quest, nft.party_id);
+        let (xp, fee) = match nft.staked_date {
+            Some(_) => ((xp_reward as i64 * std::cmp::min(policy.xp_percent, 100) as i64 / 100) as i32, policy.shill_fee),
+            None => (0, Uint128::from(0u32))
+        };
+        if nft.staked_date.is_none() {
+            if let Some(party_id) = nft.party_id {
+                if !left_parties.contains(&party_id) {
+                    left_parties.push(party_id);
+                }
+            }
+        }
+
+        charge_abandon_fee(deps.storage, &state, &quest, &sender_raw, fee)?;
+        // entry fees are forfeited, except by wolves that never started hunting
+        let entry_fee = release_entry_fee(deps.storage, &state, &quest, &sender_raw, nft.staked_date.is_none())?;
+        if let Some(msg) = route_entry_fee(&state, &mut quest, entry_fee)? {
+            response_msgs.push(msg);
+        }
+        release_reward(&state, &mut quest, nft.party_id, Uint128::from(0u32))?;
+        remove_wolf(deps.storage, &mut quest, &nft, false)?;
+        save_quest(deps.storage, &quest)?;
+        let mut level: Option<i32> = None;
+        if xp > 0 {
+            let progress_store = wolf_progress_store(&collection.contract.address);
+            let tracked = progress_store.get(deps.storage, token_id);
+            let new_ext = if state.sync_metadata || tracked.is_none() {
+                let viewer = Some(ViewerInfo {
+                    address: _env.contract.address.to_string(),
+                    viewing_key: collection.viewing_key.clone(),
+                });
+                let meta: NftDossier = nft_dossier_query(
+                    deps.querier,
+                    token_id.to_string(),
+                    viewer,
+                    None,
+                    BLOCK_SIZE,
+                    collection.contract.code_hash.clone(),
+                    collection.contract.address.to_string(),
+                )?;
+                Some(progress_extension(meta, token_id, &state)?)
+            } else {
+                None
+            };
+            let mut progress = current_progress(tracked, new_ext.as_ref(), token_id, &state, &levels)?;
+            let (_, new_lvl) = add_xp(&mut progress, xp, &levels, state.level_cap);
+            progress_store.insert(deps.storage, token_id, &progress)?;
+            level = Some(new_lvl);
+
+            if let (Some(mut ext), true) = (new_ext, state.sync_metadata) {
+                write_progress(&mut ext, &progress, &state);
+                response_msgs.push(progress_metadata_msg(token_id, ext, &state, &collection)?);
+            }
+        }
+        fee_to_pay = fee_to_pay.checked_add(fee).map_err(StdError::from)?;
 
-    if sender.clone() == state.owner {
-        ADMIN_VIEWING_KEY_ITEM.save(deps.storage, &vk)?;
-    }  
-    else{
-        VIEWING_KEY_STORE.insert(deps.storage, &deps.api.addr_canonicalize(&sender.to_string())?, &vk)?;
+        staked_history_store(&sender_raw).push(deps.storage, &HistoryToken {
+            token_id: nft.token_id,
+            owner: nft.owner,
+            sender: nft.sender,
+            collection: nft.collection,
+            quest_id: nft.quest_id,
+            party_id: nft.party_id,
+            staked_date: nft.staked_date,
+            claimed_date: Some(current_time),
+            reward_amount: Uint128::from(0u32),
+            xp_reward: xp,
+            base_reward: Uint128::from(0u32),
+            bonus_reward: Uint128::from(0u32),
+            level,
+            abandoned: true,
+            roll: None,
+            loot: vec![]
+        })?;
     }
- 
-    Ok(Response::default())
+
+    response_msgs.push(batch_transfer_nft_msg(
+        vec![Transfer {
+            recipient: sender.to_string(),
+            token_ids: token_ids.clone(),
+            memo: None
+        }],
+        None,
+        BLOCK_SIZE,
+        collection.contract.code_hash.clone(),
+        collection.contract.address.to_string(),
+    )?);
+
+    staked_nfts_store().insert(deps.storage, &sender_raw, &staked_nfts)?;
+
+    // members leave a party once none of their wolves are waiting in it
+    for party_id in left_parties {
+        if !staked_nfts.iter().any(|x| x.party_id == Some(party_id)) {
+            leave_party(deps.storage, party_id, sender)?;
+        }
+    }
+
+    Ok(Response::new()
+        .add_messages(response_msgs)
+        .add_attribute("abandoned_wolves", token_ids.len().to_string())
+        .add_attribute("shill_fee", fee_to_pay.to_string())
+    )
 }
 
-pub fn try_send_shill_back(
+pub fn try_add_collection(
     deps: DepsMut,
     _env: Env,
     sender: &Addr,
-    amount: Uint128,
-    address: Addr
-) -> Result<Response, ContractError> {  
-    let state = CONFIG_ITEM.load(deps.storage)?;
+    contract: ContractInfo
+) -> Result<Response, ContractError> {
+    let mut state = CONFIG_ITEM.load(deps.storage)?;
     if sender.clone() != state.owner {
         return Err(ContractError::Unauthorized {});
     }
-   
-    Ok(Response::new().add_message(
-        transfer_msg(
-            address.to_string(),
-            amount,
-            None,
+    if state.collection(&contract.address).is_some() {
+        return Err(ContractError::CollectionExists { collection: contract.address.to_string() });
+    }
+
+    // every collection gets its own viewing key
+    let prng_seed = PRNG_SEED_ITEM.load(deps.storage)?;
+    let mut rng = Prng::new(&prng_seed, contract.address.as_bytes());
+    let viewing_key = base64::encode(rng.rand_bytes());
+    PRNG_SEED_ITEM.save(deps.storage, &rng.rand_bytes().to_vec())?;
+
+    state.collections.push(Collection {
+        contract: contract.clone(),
+        viewing_key: viewing_key.clone()
+    });
+    CONFIG_ITEM.save(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_message(register_receive_nft_msg(
+            _env.contract.code_hash,
+            Some(true),
             None,
-            256,
-            state.shill_contract.code_hash.to_string(),
-            state.shill_contract.address.to_string()
+            BLOCK_SIZE,
+            contract.code_hash.clone(),
+            contract.address.to_string(),
         )?)
-    ) 
+        .add_message(set_viewing_key_msg(
+            viewing_key,
+            None,
+            BLOCK_SIZE,
+            contract.code_hash,
+            contract.address.to_string(),
+        )?)
+    )
 }
 
-#[entry_point]
-pub fn query(
-    deps: Deps,
+pub fn try_receive(
+    deps: DepsMut,
     _env: Env,
-    msg: QueryMsg,
-) -> StdResult<Binary> {
-    match msg { 
-        QueryMsg::GetQuests {} => to_binary(&query_quests(deps)?),
-        QueryMsg::GetState {viewer} => to_binary(&query_state(deps, viewer)?),
-        QueryMsg::GetUserStakedNfts {permit} => to_binary(&query_user_staked_nfts(deps, permit)?),
-        QueryMsg::GetNumUserStakedNftHistory { permit } => to_binary(&query_num_user_staked_nft_history(deps, permit)?),
-        QueryMsg::GetUserStakedNftHistory {permit, start_page, page_size} => to_binary(&query_user_staked_nft_history(deps, permit, start_page, page_size)?),
-        QueryMsg::GetNumStakedNftKeys { viewer } => to_binary(&query_num_staked_keys(deps, viewer)?),
-        QueryMsg::GetStakedNfts { viewer, start_page, page_size } => to_binary(&query_staked_nfts(deps, viewer, start_page, page_size)?)
-       
-        
+    sender: &Addr,
+    from: &Addr,
+    amount: Uint128,
+    msg: Option<Binary>
+) -> Result<Response, ContractError> {
+    // only registered SNIP-20 contracts are allowed to report received tokens
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    if state.fee_token(sender).is_none() {
+        return Err(ContractError::UnknownFeeToken { token: sender.to_string() });
+    }
+
+    let bin = msg.ok_or(ContractError::InvalidReceiveMsg {})?;
+    let rmsg: ReceiveMsg = serde_json::from_slice(bin.as_slice()).map_err(|_| ContractError::InvalidReceiveMsg {})?;
+    match rmsg {
+        ReceiveMsg::PayEntryFee { quest_id } => receive_entry_fee(deps, sender, from, amount, quest_id),
+        ReceiveMsg::FundQuest { quest_id } => receive_quest_funds(deps, &state, sender, amount, quest_id)
     }
 }
- 
-fn query_quests(
-    deps: Deps,
-) -> StdResult<QuestResponse> {
- 
+
+fn receive_entry_fee(
+    deps: DepsMut,
+    sender: &Addr,
+    from: &Addr,
+    amount: Uint128,
+    quest_id: i32
+) -> Result<Response, ContractError> {
     let state = CONFIG_ITEM.load(deps.storage)?;
-    Ok(QuestResponse { quests: state.quests })
-}
+    let quest = load_quest(deps.storage, quest_id)?;
+    if quest.closed {
+        return Err(ContractError::QuestClosed { quest_id: quest.quest_id });
+    }
+    let token = escrow_token(&state, &quest)?;
+    if &token.address != sender {
+        return Err(ContractError::WrongFeeToken { quest_id: quest.quest_id, token: sender.to_string() });
+    }
 
+    let from_raw = deps.api.addr_canonicalize(from.as_str())?;
+    let mut escrow = entry_fee_store(quest.quest_id).get(deps.storage, &from_raw).unwrap_or_default();
+    escrow.paid = escrow.paid.checked_add(amount).map_err(StdError::from)?;
+    save_escrow(deps.storage, &state, &quest, &from_raw, &escrow)?;
 
-fn query_state(
-    deps: Deps,
-    viewer: ViewerInfo
-) -> StdResult<State> {
-    check_admin_key(deps, viewer)?;
+    Ok(Response::new().add_attribute("entry_fee_paid", escrow.paid.to_string()))
+}
 
-    let state = CONFIG_ITEM.load(deps.storage)?;  
+fn receive_quest_funds(
+    deps: DepsMut,
+    state: &State,
+    sender: &Addr,
+    amount: Uint128,
+    quest_id: i32
+) -> Result<Response, ContractError> {
+    if sender != &state.shill_contract.address {
+        return Err(ContractError::WrongRewardToken { token: sender.to_string() });
+    }
 
-    Ok(state)
-}
- 
-fn query_user_staked_nfts(
-    deps: Deps, 
-    permit: Permit
-) -> StdResult<Vec<Token>> { 
-    let (user_raw, my_addr) = get_querier(deps, permit)?;
+    let mut quest = load_quest(deps.storage, quest_id)?;
+    quest.reward_pool = quest.reward_pool.checked_add(amount).map_err(StdError::from)?;
+    save_quest(deps.storage, &quest)?;
 
-    let staked_nfts = STAKED_NFTS_STORE.get(deps.storage, &user_raw).unwrap();
- 
-    Ok(staked_nfts)
+    Ok(Response::new().add_attribute("reward_pool", quest.reward_pool.to_string()))
 }
 
-fn query_num_staked_keys(
-    deps: Deps, 
-    viewer: ViewerInfo
-) -> StdResult<u32> {
-    check_admin_key(deps, viewer)?;
-    let num_staked_keys = STAKED_NFTS_STORE.get_len(deps.storage).unwrap();
+pub fn try_add_fee_token(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    contract: ContractInfo
+) -> Result<Response, ContractError> {
+    let mut state = CONFIG_ITEM.load(deps.storage)?;
+    if sender.clone() != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    Ok(num_staked_keys)
-}
+    // adding a registered token again only registers this contract as its receiver again
+    if state.fee_token(&contract.address).is_none() {
+        state.fee_tokens.push(contract.clone());
+        CONFIG_ITEM.save(deps.storage, &state)?;
+    }
 
-fn query_staked_nfts(
-    deps: Deps, 
-    viewer: ViewerInfo,
-    start_page: u32, 
-    page_size: u32
-) -> StdResult<Vec<(CanonicalAddr,Vec<Token>)>> {
-    check_admin_key(deps, viewer)?; 
-    let staked_nfts = STAKED_NFTS_STORE.paging(deps.storage, start_page, page_size)?;
-    Ok(staked_nfts)
+    Ok(Response::new()
+        .add_message(register_receive_msg(
+            _env.contract.code_hash,
+            None,
+            BLOCK_SIZE,
+            contract.code_hash,
+            contract.address.to_string(),
+        )?)
+    )
 }
 
-fn query_user_staked_nft_history(
-    deps: Deps, 
-    permit: Permit,
-    start_page: u32, 
-    page_size: u32
-) -> StdResult<Vec<HistoryToken>> {
-    let (user_raw, my_addr) = get_querier(deps, permit)?;
-    
-    let staked_history_store = STAKED_NFTS_HISTORY_STORE.add_suffix(&user_raw);
-    //let staked_history_store = STAKED_NFTS_HISTORY_STORE.add_suffix(viewer.address.to_string().as_bytes());
-    let history = staked_history_store.paging(deps.storage, start_page, page_size)?;
-    Ok(history)
-}
+pub fn try_withdraw_entry_fee(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    quest_id: i32
+) -> Result<Response, ContractError> {
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    let quest = load_quest(deps.storage, quest_id)?;
+    let token = escrow_token(&state, &quest)?;
 
-fn query_num_user_staked_nft_history(
-    deps: Deps, 
-    permit: Permit
-) -> StdResult<u32> { 
-    let (user_raw, my_addr) = get_querier(deps, permit)?;
-    let staked_history_store = STAKED_NFTS_HISTORY_STORE.add_suffix(&user_raw);
-    let num = staked_history_store.get_len(deps.storage)?;
-    Ok(num)
-} 
+    let sender_raw = deps.api.addr_canonicalize(sender.as_str())?;
+    let mut escrow = entry_fee_store(quest_id).get(deps.storage, &sender_raw).unwrap_or_default();
+    let amount = escrow.paid;
+    if amount.is_zero() {
+        let required = quest.entry_fee.map_or(quest.abandon_policy.shill_fee, |x| x.amount);
+        return Err(ContractError::EntryFeeNotPaid { quest_id, required, paid: amount });
+    }
+    escrow.paid = Uint128::from(0u32);
+    save_escrow(deps.storage, &state, &quest, &sender_raw, &escrow)?;
 
-fn check_admin_key(deps: Deps, viewer: ViewerInfo) -> StdResult<()> {
-    let admin_viewing_key = ADMIN_VIEWING_KEY_ITEM.load(deps.storage)?;  
-    let prng_seed: Vec<u8> = sha_256(base64::encode(viewer.viewing_key).as_bytes()).to_vec();
-    let vk = base64::encode(&prng_seed);
+    Ok(Response::new()
+        .add_message(transfer_msg(
+            sender.to_string(),
+            amount,
+            None,
+            None,
+            BLOCK_SIZE,
+            token.code_hash.clone(),
+            token.address.to_string()
+        )?)
+        .add_attribute("entry_fee_withdrawn", amount.to_string())
+    )
+}
 
-    if vk != admin_viewing_key.viewing_key || viewer.address != admin_viewing_key.address{
-        return Err(StdError::generic_err(
-            "Wrong viewing key for this address or viewing key not set",
-        )); 
+pub fn try_set_levels(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    levels: Vec<Level>
+) -> Result<Response, ContractError> {
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    if sender.clone() != state.owner {
+        return Err(ContractError::Unauthorized {});
     }
 
-    return Ok(());
+    validate_levels(&levels, state.level_cap)?;
+    LEVEL_ITEM.save(deps.storage, &levels)?;
+    Ok(Response::default())
 }
 
-fn get_querier(
-    deps: Deps,
-    permit: Permit,
-) -> StdResult<(CanonicalAddr, Option<CanonicalAddr>)> {
-    if let pmt = permit {
-        let me_raw: CanonicalAddr = MY_ADDRESS_ITEM.load(deps.storage)?;
-        let my_address = deps.api.addr_humanize(&me_raw)?;
-        let querier = deps.api.addr_canonicalize(&validate(
-            deps,
-            PREFIX_REVOKED_PERMITS,
-            &pmt,
-            my_address.to_string(),
-            None
-        )?)?;
-        if !pmt.check_permission(&secret_toolkit::permit::TokenPermissions::Owner) {
-            return Err(StdError::generic_err(format!(
-                "Owner permission is required for Stashh minter queries, got permissions {:?}",
-                pmt.params.permissions
-            )));
-        }
-        return Ok((querier, Some(me_raw)));
+pub fn try_add_levels(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    levels: Vec<Level>
+) -> Result<Response, ContractError> {
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    if sender.clone() != state.owner {
+        return Err(ContractError::Unauthorized {});
     }
-    return Err(StdError::generic_err(
-        "Unauthorized",
-    ));  
+
+    let mut all_levels = LEVEL_ITEM.load(deps.storage)?;
+    all_levels.extend(levels);
+    validate_levels(&all_levels, state.level_cap)?;
+    LEVEL_ITEM.save(deps.storage, &all_levels)?;
+    Ok(Response::default())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+pub fn try_set_level_cap(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    level_cap: i32
+) -> Result<Response, ContractError> {
+    let mut state = CONFIG_ITEM.load(deps.storage)?;
+    if sender.clone() != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coins, from_binary};  
-    #[test]
-    fn proper_initialization() {
-        let mut deps = mock_dependencies();
+    let levels = LEVEL_ITEM.load(deps.storage)?;
+    validate_levels(&levels, level_cap)?;
+    state.level_cap = level_cap;
+    CONFIG_ITEM.save(deps.storage, &state)?;
+    Ok(Response::default())
+}
 
-        let msg = InstantiateMsg { entropy:"wolfpack".to_string(), quest_contract:{ContractInfo{address:Addr::unchecked("secret174kgn5rtw4kf6f938wm7kwh70h2v4vcfft5mqy"), code_hash:"45f450a4277570f8d1a81eb1185e17ce042a217227dfd836a613c7e54ac15447".to_string()} }};
-        let info = mock_info("creator", &coins(1000, "earth"));
-       
-        // we can just call .unwrap() to assert this was a success
-        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-        println!("{} dayknnkjnjknkjn kjnknkjnk nkjnkjs", 31);
- 
-        assert_eq!(2, res.messages.len());
+pub fn try_set_progress_traits(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    xp_trait: String,
+    level_trait: String,
+    private: bool
+) -> Result<Response, ContractError> {
+    let mut state = CONFIG_ITEM.load(deps.storage)?;
+    if sender.clone() != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
 
-        // it worked, let's query the state
-        
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCount {}).unwrap();
-        let value: CountResponse = from_binary(&res).unwrap();
-        assert_eq!(17, value.count);
+    if xp_trait.is_empty() || level_trait.is_empty() || xp_trait == level_trait {
+        return Err(ContractError::InvalidProgressTraits {});
     }
+    state.xp_trait = xp_trait;
+    state.level_trait = level_trait;
+    state.private_progress = private;
+    CONFIG_ITEM.save(deps.storage, &state)?;
+    Ok(Response::default())
+}
 
-    #[test]
-    fn increment() {
-        let mut deps = mock_dependencies();
-    
-        let msg = InstantiateMsg { count: 17, entropy:"wolfpack".to_string(), quest_contract:{ContractInfo{ address:Addr::unchecked("secret174kgn5rtw4kf6f938wm7kwh70h2v4vcfft5mqy"), code_hash:"45f450a4277570f8d1a81eb1185e17ce042a217227dfd836a613c7e54ac15447".to_string()} }};
-        let info = mock_info("creator", &coins(2, "token"));
-        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-    
-        // anyone can increment
-        let info = mock_info("anyone", &coins(2, "token"));
-        let msg = ExecuteMsg::Increment {};
-        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    
-        // should increase counter by 1
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCount {}).unwrap();
-        let value: CountResponse = from_binary(&res).unwrap();
-        assert_eq!(18, value.count);
+pub fn try_set_metadata_sync(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    sync: bool
+) -> Result<Response, ContractError> {
+    let mut state = CONFIG_ITEM.load(deps.storage)?;
+    if sender.clone() != state.owner {
+        return Err(ContractError::Unauthorized {});
     }
-    
-    #[test]
-    fn reset() {
-        let mut deps = mock_dependencies();
-    
-        let msg = InstantiateMsg { count: 17, entropy:"wolfpack".to_string(), quest_contract:{ContractInfo{address:Addr::unchecked("secret174kgn5rtw4kf6f938wm7kwh70h2v4vcfft5mqy"), code_hash:"45f450a4277570f8d1a81eb1185e17ce042a217227dfd836a613c7e54ac15447".to_string()} }};
-        let info = mock_info("creator", &coins(2, "token"));
-        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-    
-        // not anyone can reset
-        let unauth_env = mock_info("anyone", &coins(2, "token"));
-        let msg = ExecuteMsg::Reset { count: 5 };
-        let res = execute(deps.as_mut(), mock_env(), unauth_env, msg);
-        match res {
-            Err(ContractError::Unauthorized {}) => {}
-            _ => panic!("Must return unauthorized error"),
-        }
-    
-        // only the original creator can reset the counter
-        let auth_info = mock_info("creator", &coins(2, "token"));
-        let msg = ExecuteMsg::Reset { count: 5 };
-        let _res = execute(deps.as_mut(), mock_env(), auth_info, msg).unwrap();
-    
-        // should now be 5
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCount {}).unwrap();
-        let value: CountResponse = from_binary(&res).unwrap();
-        assert_eq!(5, value.count);
+
+    state.sync_metadata = sync;
+    CONFIG_ITEM.save(deps.storage, &state)?;
+    Ok(Response::default())
+}
+
+pub fn try_propose_owner(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    address: Addr
+) -> Result<Response, ContractError> {
+    let mut state = CONFIG_ITEM.load(deps.storage)?;
+    if sender.clone() != state.owner {
+        return Err(ContractError::Unauthorized {});
     }
 
-    // #[test]
-    // fn try_batch_receive() {
-    //     let mut deps = mock_dependencies();
-    
-    //     let msg = BatchReceiveNft { };
-    //     let info = mock_info("creator", &coins(2, "token"));
-    //     let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-    
-    //     // anyone can increment
-    //     let info = mock_info("anyone", &coins(2, "token"));
-    //     let msg = ExecuteMsg::BatchReceiveNft {from:"afaf", msg: "eyJxdWVzdF9pZCI6MX0=".to_string(), token_ids: ["4"]};
-    //     let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-    
-    //     // // should increase counter by 1
-    //     // let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCount {}).unwrap();
-    //     // let value: CountResponse = from_binary(&res).unwrap();
-    //     // assert_eq!(18, value.count);
-    // }
-    
+    state.pending_owner = Some(deps.api.addr_validate(address.as_str())?);
+    CONFIG_ITEM.save(deps.storage, &state)?;
+    Ok(Response::default())
+}
+
+pub fn try_accept_ownership(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr
+) -> Result<Response, ContractError> {
+    let mut state = CONFIG_ITEM.load(deps.storage)?;
+    match &state.pending_owner {
+        Some(pending_owner) if pending_owner == sender => {},
+        Some(_) => return Err(ContractError::Unauthorized {}),
+        None => return Err(ContractError::NoPendingOwner {})
+    }
+
+    state.owner = sender.clone();
+    state.pending_owner = None;
+    CONFIG_ITEM.save(deps.storage, &state)?;
+    ADMIN_ITEM.save(deps.storage, &deps.api.addr_canonicalize(sender.as_str())?)?;
+    Ok(Response::new().add_attribute("owner", sender.to_string()))
 }
 
+pub fn try_grant_role(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    address: Addr,
+    role: Role
+) -> Result<Response, ContractError> {
+    let mut state = CONFIG_ITEM.load(deps.storage)?;
+    if sender.clone() != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let address = deps.api.addr_validate(address.as_str())?;
+    if !state.roles.iter().any(|x| x.address == address && x.role == role) {
+        state.roles.push(RoleGrant { address, role });
+        CONFIG_ITEM.save(deps.storage, &state)?;
+    }
+    Ok(Response::default())
+}
 
+pub fn try_revoke_role(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    address: Addr,
+    role: Role
+) -> Result<Response, ContractError> {
+    let mut state = CONFIG_ITEM.load(deps.storage)?;
+    if sender.clone() != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    state.roles.retain(|x| x.address != address || x.role != role);
+    CONFIG_ITEM.save(deps.storage, &state)?;
+    Ok(Response::default())
+}
+
+pub fn try_set_viewing_key(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    key: String
+) -> Result<Response, ContractError> {
+    save_viewing_key(deps, sender, key)?;
+    Ok(Response::default())
+}
+
+pub fn try_create_viewing_key(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    entropy: String
+) -> Result<Response, ContractError> {
+    let mut rng = sender_rng(deps.storage, &_env, sender, Some(entropy))?;
+    let key = "api_key_".to_string() + &base64::encode(rng.rand_bytes());
+    // move the seed forward so the same entropy in the same block gives another key
+    PRNG_SEED_ITEM.save(deps.storage, &rng.rand_bytes().to_vec())?;
+    save_viewing_key(deps, sender, key.clone())?;
+
+    Ok(Response::new().set_data(to_binary(&ExecuteAnswer::CreateViewingKey { key })?))
+}
+
+pub fn try_revoke_permit(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    permit_name: String
+) -> Result<Response, ContractError> {
+    RevokedPermits::revoke_permit(deps.storage, PREFIX_REVOKED_PERMITS, sender.as_str(), &permit_name);
+    Ok(Response::default())
+}
+
+/// Stores the hash of a viewing key, admins keep their keys with the users'
+fn save_viewing_key(
+    deps: DepsMut,
+    sender: &Addr,
+    key: String
+) -> StdResult<()> {
+    let vk: ViewerInfo = { ViewerInfo {
+        address: sender.to_string(),
+        viewing_key: hash_viewing_key(key),
+    } };
+    viewing_key_store().insert(deps.storage, &deps.api.addr_canonicalize(sender.as_ref())?, &vk)?;
+    Ok(())
+}
+
+pub fn try_send_shill_back(
+    deps: DepsMut,
+    _env: Env,
+    sender: &Addr,
+    amount: Uint128,
+    address: Addr
+) -> Result<Response, ContractError> {  
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    if !state.has_role(sender, Role::Treasurer) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // SHILL owed to players stays in the contract
+    let funds = solvency(deps.as_ref(), &_env, &state)?;
+    if amount > funds.free_balance {
+        return Err(ContractError::InsufficientFreeBalance { available: funds.free_balance });
+    }
+   
+    Ok(Response::new().add_message(
+        transfer_msg(
+            address.to_string(),
+            amount,
+            None,
+            None,
+            256,
+            state.shill_contract.code_hash.to_string(),
+            state.shill_contract.address.to_string()
+        )?)
+    ) 
+}
+
+/// Level tables have to be contiguous with strictly increasing XP, and the level
+/// cap has to be one of their levels
+fn validate_levels(
+    levels: &[Level],
+    level_cap: i32
+) -> Result<(), ContractError> {
+    if levels.is_empty() {
+        return Err(ContractError::NoLevels {});
+    }
+    for pair in levels.windows(2) {
+        if pair[1].level != pair[0].level + 1 {
+            return Err(ContractError::NonContiguousLevel { level: pair[1].level });
+        }
+        if pair[1].xp_needed <= pair[0].xp_needed {
+            return Err(ContractError::XpNotIncreasing { level: pair[1].level });
+        }
+    }
+    if !levels.iter().any(|x| x.level == level_cap) {
+        return Err(ContractError::InvalidLevelCap { level_cap });
+    }
+    Ok(())
+}
+
+/// Returns the registered collection with the given address, or the collection
+/// the contract was instantiated with when no address is given
+fn resolve_collection(
+    state: &State,
+    collection: Option<Addr>
+) -> Result<Collection, ContractError> {
+    match collection {
+        Some(address) => state.collection(&address).cloned()
+            .ok_or(ContractError::UnknownCollection { collection: address.to_string() }),
+        None => state.collections.first().cloned()
+            .ok_or(ContractError::UnknownCollection { collection: String::new() })
+    }
+}
+
+fn load_quest(
+    storage: &dyn Storage,
+    quest_id: i32
+) -> Result<Quest, ContractError> {
+    quests_store().get(storage, &quest_id).ok_or(ContractError::QuestNotFound { quest_id })
+}
+
+/// Adds a duration in seconds to a block time
+fn add_seconds(
+    time: u64,
+    seconds: u64
+) -> StdResult<u64> {
+    Ok(Uint64::from(time).checked_add(Uint64::from(seconds))?.u64())
+}
+
+/// Drops a wolf from the token index, wolves staked before the index existed
+/// may have no entry and count as already removed
+fn unindex_wolf(
+    storage: &mut dyn Storage,
+    collection: &Addr,
+    token_id: &str
+) -> StdResult<()> {
+    let token_index = token_index_store(collection);
+    let token_id = token_id.to_string();
+    if token_index.contains(storage, &token_id) {
+        token_index.remove(storage, &token_id)?;
+    }
+    Ok(())
+}
+
+/// Drops a wolf leaving a quest from the token and participant indexes and
+/// updates the quest's counters, the quest still has to be saved
+fn remove_wolf(
+    storage: &mut dyn Storage,
+    quest: &mut Quest,
+    nft: &Token,
+    completed: bool
+) -> StdResult<()> {
+    unindex_wolf(storage, &nft.collection, &nft.token_id)?;
+    let participants = quest_participants_store(quest.quest_id);
+    let key = (nft.collection.clone(), nft.token_id.clone());
+    if participants.contains(storage, &key) {
+        participants.remove(storage, &key)?;
+    }
+    quest.wolves_on_the_hunt = std::cmp::max(quest.wolves_on_the_hunt - 1, 0);
+    if completed {
+        quest.wolves_completed += 1;
+    }
+    Ok(())
+}
+
+/// Checks a wolf joining a quest against the quest's trait and level requirements
+fn check_requirements(
+    quest: &Quest,
+    token_id: &str,
+    dossier: NftDossier,
+    tracked: Option<WolfProgress>,
+    state: &State,
+    levels: &[Level]
+) -> Result<(), ContractError> {
+    if quest.required_traits.is_empty() && quest.min_level.is_none() && quest.max_level.is_none() {
+        return Ok(());
+    }
+
+    // XP and level requirements are checked against the ledger, not the metadata
+    let traits = dossier_traits(&dossier);
+    let progress = match tracked {
+        Some(progress) => progress,
+        None => metadata_progress(&progress_extension(dossier, token_id, state)?, token_id, state, levels)?
+    };
+    let traits = with_progress(traits, &progress, &state.xp_trait, &state.level_trait);
+    for condition in quest.required_traits.iter() {
+        if !condition_matches(condition, &traits) {
+            return Err(ContractError::RequirementNotMet { token_id: token_id.to_string(), trait_type: condition.trait_type.clone() });
+        }
+    }
+
+    let level = progress.level;
+    if let Some(min_level) = quest.min_level {
+        if level < min_level {
+            return Err(ContractError::LevelTooLow { token_id: token_id.to_string(), level, min_level });
+        }
+    }
+    if let Some(max_level) = quest.max_level {
+        if level > max_level {
+            return Err(ContractError::LevelTooHigh { token_id: token_id.to_string(), level, max_level });
+        }
+    }
+    Ok(())
+}
+
+/// Adds a member to a party of the quest, opening a new party when no id is
+/// given. The party is marked formed once it is full
+fn join_party(
+    storage: &mut dyn Storage,
+    quest: &Quest,
+    party_id: Option<u32>,
+    member: &Addr,
+    current_time: u64
+) -> Result<Party, ContractError> {
+    let mut party = match party_id {
+        Some(party_id) => {
+            let party = parties_store().get(storage, &party_id)
+                .filter(|x| x.quest_id == quest.quest_id)
+                .ok_or(ContractError::PartyNotFound { party_id })?;
+            if party.members.contains(member) {
+                return Err(ContractError::AlreadyInParty { party_id });
+            }
+            if party.formed_date.is_some() {
+                return Err(ContractError::PartyFull { party_id });
+            }
+            party
+        },
+        None => {
+            let party_id = PARTY_COUNT_ITEM.may_load(storage)?.unwrap_or(0) + 1;
+            PARTY_COUNT_ITEM.save(storage, &party_id)?;
+            Party {
+                party_id,
+                quest_id: quest.quest_id,
+                leader: member.clone(),
+                members: vec![],
+                formed_date: None
+            }
+        }
+    };
+
+    party.members.push(member.clone());
+    if party.members.len() as i32 >= quest.party_size {
+        party.formed_date = Some(current_time);
+    }
+    parties_store().insert(storage, &party.party_id, &party)?;
+    Ok(party)
+}
+
+/// Removes a member from a party that hasn't started hunting, the party is
+/// disbanded when its last member leaves
+fn leave_party(
+    storage: &mut dyn Storage,
+    party_id: u32,
+    member: &Addr
+) -> Result<(), ContractError> {
+    if let Some(mut party) = parties_store().get(storage, &party_id) {
+        party.members.retain(|x| x != member);
+        match party.members.first().cloned() {
+            Some(next) => {
+                if &party.leader == member {
+                    party.leader = next;
+                }
+                parties_store().insert(storage, &party_id, &party)?;
+            },
+            None => parties_store().remove(storage, &party_id)?
+        }
+    }
+    Ok(())
+}
+
+/// Takes the entry fee of one wolf out of its owner's locked fees. Refunded fees
+/// go back to the owner's unused fees, otherwise the fee is returned to be routed
+fn release_entry_fee(
+    storage: &mut dyn Storage,
+    state: &State,
+    quest: &Quest,
+    owner_raw: &CanonicalAddr,
+    refund: bool
+) -> StdResult<Uint128> {
+    let entry_fee = match &quest.entry_fee {
+        Some(entry_fee) => entry_fee,
+        None => return Ok(Uint128::from(0u32))
+    };
+    let fee_store = entry_fee_store(quest.quest_id);
+    let mut escrow = match fee_store.get(storage, owner_raw) {
+        Some(escrow) => escrow,
+        None => return Ok(Uint128::from(0u32))
+    };
+
+    let amount = std::cmp::min(entry_fee.amount, escrow.locked);
+    escrow.locked -= amount;
+    if refund {
+        escrow.paid = escrow.paid.checked_add(amount)?;
+    }
+    save_escrow(storage, state, quest, owner_raw, &escrow)?;
+    Ok(if refund { Uint128::from(0u32) } else { amount })
+}
+
+/// Takes the abandon fee of one wolf out of the SHILL its owner paid into the
+/// quest's escrow, the fee stays with the contract
+fn charge_abandon_fee(
+    storage: &mut dyn Storage,
+    state: &State,
+    quest: &Quest,
+    owner_raw: &CanonicalAddr,
+    fee: Uint128
+) -> Result<(), ContractError> {
+    if fee.is_zero() {
+        return Ok(());
+    }
+    let mut escrow = entry_fee_store(quest.quest_id).get(storage, owner_raw).unwrap_or_default();
+    if escrow.paid < fee {
+        return Err(ContractError::AbandonFeeNotPaid { quest_id: quest.quest_id, required: fee, paid: escrow.paid });
+    }
+    escrow.paid -= fee;
+    save_escrow(storage, state, quest, owner_raw, &escrow)?;
+    Ok(())
+}
+
+/// The token a quest's escrow is paid in, quests without an entry fee only
+/// take SHILL to cover their abandon fee
+fn escrow_token(
+    state: &State,
+    quest: &Quest
+) -> Result<ContractInfo, ContractError> {
+    match &quest.entry_fee {
+        Some(entry_fee) => state.fee_token(&entry_fee.token).cloned()
+            .ok_or(ContractError::UnknownFeeToken { token: entry_fee.token.to_string() }),
+        None if !quest.abandon_policy.shill_fee.is_zero() => Ok(state.shill_contract.clone()),
+        None => Err(ContractError::NoEntryFee { quest_id: quest.quest_id })
+    }
+}
+
+/// Sends a released entry fee where the quest routes its fees, fees added to
+/// the reward pool only change the quest
+fn route_entry_fee(
+    state: &State,
+    quest: &mut Quest,
+    amount: Uint128
+) -> Result<Option<CosmosMsg>, ContractError> {
+    let entry_fee = match &quest.entry_fee {
+        Some(entry_fee) if !amount.is_zero() => entry_fee,
+        _ => return Ok(None)
+    };
+    let token = state.fee_token(&entry_fee.token)
+        .ok_or(ContractError::UnknownFeeToken { token: entry_fee.token.to_string() })?;
+
+    let msg = match &entry_fee.destination {
+        FeeDestination::Treasury { address } => transfer_msg(
+            address.to_string(),
+            amount,
+            None,
+            None,
+            BLOCK_SIZE,
+            token.code_hash.clone(),
+            token.address.to_string()
+        )?,
+        FeeDestination::Burn {} => burn_msg(
+            amount,
+            None,
+            None,
+            BLOCK_SIZE,
+            token.code_hash.clone(),
+            token.address.to_string()
+        )?,
+        FeeDestination::RewardPool {} => {
+            quest.reward_pool = quest.reward_pool.checked_add(amount).map_err(StdError::from)?;
+            return Ok(None);
+        }
+    };
+    Ok(Some(msg))
+}
+
+/// Releases the reservation of a wolf leaving the quest, what it wasn't paid
+/// goes back to the reward pool
+fn release_reward(
+    state: &State,
+    quest: &mut Quest,
+    party_id: Option<u32>,
+    paid: Uint128
+) -> StdResult<()> {
+    // wolves that joined before rewards were reserved have nothing to release
+    let reservation = std::cmp::min(max_wolf_reward(quest, party_id, &state.shill_contract.address)?, quest.reserved);
+    quest.reserved -= reservation;
+    quest.reward_pool = quest.reward_pool.checked_add(reservation.saturating_sub(paid))?;
+    Ok(())
+}
+
+/// Saves a quest and moves the change of its reward pool and reservations into
+/// the running totals
+fn save_quest(
+    storage: &mut dyn Storage,
+    quest: &Quest
+) -> StdResult<()> {
+    let store = quests_store();
+    let mut funds = FUNDS_ITEM.may_load(storage)?.unwrap_or_default();
+    if let Some(old) = store.get(storage, &quest.quest_id) {
+        funds.reward_pools = funds.reward_pools.saturating_sub(old.reward_pool);
+        funds.reserved = funds.reserved.saturating_sub(old.reserved);
+    }
+    funds.reward_pools = funds.reward_pools.checked_add(quest.reward_pool)?;
+    funds.reserved = funds.reserved.checked_add(quest.reserved)?;
+    FUNDS_ITEM.save(storage, &funds)?;
+    store.insert(storage, &quest.quest_id, quest)
+}
+
+/// Removes a quest, whatever was left in its pool is no longer owed
+fn remove_quest(
+    storage: &mut dyn Storage,
+    quest: &Quest
+) -> StdResult<()> {
+    let mut funds = FUNDS_ITEM.may_load(storage)?.unwrap_or_default();
+    funds.reward_pools = funds.reward_pools.saturating_sub(quest.reward_pool);
+    funds.reserved = funds.reserved.saturating_sub(quest.reserved);
+    FUNDS_ITEM.save(storage, &funds)?;
+    quests_store().remove(storage, &quest.quest_id)
+}
+
+/// Saves what a user holds in a quest's escrow, empty escrows are removed. Only
+/// escrows held in SHILL count towards the running totals
+fn save_escrow(
+    storage: &mut dyn Storage,
+    state: &State,
+    quest: &Quest,
+    owner_raw: &CanonicalAddr,
+    escrow: &EntryFeeEscrow
+) -> StdResult<()> {
+    let fee_store = entry_fee_store(quest.quest_id);
+    let old = fee_store.get(storage, owner_raw);
+    if matches!(escrow_token(state, quest), Ok(token) if token.address == state.shill_contract.address) {
+        let mut funds = FUNDS_ITEM.may_load(storage)?.unwrap_or_default();
+        if let Some(old) = &old {
+            funds.entry_fees = funds.entry_fees.saturating_sub(old.paid.checked_add(old.locked)?);
+        }
+        funds.entry_fees = funds.entry_fees.checked_add(escrow.paid.checked_add(escrow.locked)?)?;
+        FUNDS_ITEM.save(storage, &funds)?;
+    }
+
+    if !escrow.paid.is_zero() || !escrow.locked.is_zero() {
+        fee_store.insert(storage, owner_raw, escrow)?;
+    }
+    else if old.is_some() {
+        fee_store.remove(storage, owner_raw)?;
+    }
+    Ok(())
+}
+
+/// Compares the contract's SHILL balance with what it owes to players
+fn solvency(
+    deps: Deps,
+    env: &Env,
+    state: &State
+) -> StdResult<SolvencyResponse> {
+    let balance = balance_query(
+        deps.querier,
+        env.contract.address.to_string(),
+        state.shill_viewing_key.clone().unwrap_or_default(),
+        BLOCK_SIZE,
+        state.shill_contract.code_hash.clone(),
+        state.shill_contract.address.to_string()
+    )?.amount;
+
+    let funds = FUNDS_ITEM.may_load(deps.storage)?.unwrap_or_default();
+    let liabilities = funds.reward_pools
+        .checked_add(funds.reserved)?
+        .checked_add(funds.entry_fees)?
+        .checked_add(funds.loot)?;
+    Ok(SolvencyResponse {
+        balance,
+        reward_pools: funds.reward_pools,
+        reserved: funds.reserved,
+        entry_fees: funds.entry_fees,
+        loot: funds.loot,
+        free_balance: balance.saturating_sub(liabilities),
+        solvent: balance >= liabilities
+    })
+}
+
+/// Seeds an rng from the stored seed, the block and the sender's own entropy
+fn sender_rng(
+    storage: &dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    entropy: Option<String>
+) -> StdResult<Prng> {
+    let prng_seed = PRNG_SEED_ITEM.load(storage)?;
+    let mut rng_entropy: Vec<u8> = Vec::new();
+    rng_entropy.extend_from_slice(&env.block.height.to_be_bytes());
+    rng_entropy.extend_from_slice(&env.block.time.nanos().to_be_bytes());
+    rng_entropy.extend_from_slice(sender.as_bytes());
+    if let Some(entropy) = entropy {
+        rng_entropy.extend_from_slice(entropy.as_bytes());
+    }
+    Ok(Prng::new(&prng_seed, &rng_entropy))
+}
+
+/// Returns the metadata extension a wolf's XP and level are kept in, an empty
+/// one when the wolf has no metadata there yet
+fn progress_extension(
+    dossier: NftDossier,
+    token_id: &str,
+    state: &State
+) -> Result<Extension, ContractError> {
+    let metadata = if state.private_progress { dossier.private_metadata } else { dossier.public_metadata };
+    match metadata {
+        None => Ok(Extension::default()),
+        Some(Metadata { extension: Some(ext), .. }) => Ok(ext),
+        // metadata kept off-chain in a token_uri can't hold traits
+        Some(_) => Err(ContractError::MissingMetadata { token_id: token_id.to_string() })
+    }
+}
+
+/// Returns the message writing a wolf's XP and level back to the metadata they are kept in
+fn progress_metadata_msg(
+    token_id: &str,
+    ext: Extension,
+    state: &State,
+    collection: &Collection
+) -> StdResult<CosmosMsg> {
+    let metadata = Some(Metadata {
+        token_uri: None,
+        extension: Some(ext),
+    });
+    let (public_metadata, private_metadata) = if state.private_progress { (None, metadata) } else { (metadata, None) };
+    set_metadata_msg(
+        token_id.to_string(),
+        public_metadata,
+        private_metadata,
+        None,
+        BLOCK_SIZE,
+        collection.contract.code_hash.clone(),
+        collection.contract.address.to_string()
+    )
+}
+
+/// Reads a wolf's XP and level from its metadata, wolves that never went on a
+/// quest start without XP at the lowest level
+fn metadata_progress(
+    ext: &Extension,
+    token_id: &str,
+    state: &State,
+    levels: &[Level]
+) -> Result<WolfProgress, ContractError> {
+    let xp = match find_trait(ext, &state.xp_trait) {
+        Some(value) => value.parse::<i32>()
+            .map_err(|_| ContractError::InvalidXpValue { token_id: token_id.to_string(), value: value.clone() })?,
+        None => 0
+    };
+    let level = match find_trait(ext, &state.level_trait) {
+        Some(value) => value.parse::<i32>()
+            .map_err(|_| ContractError::InvalidLevelValue { token_id: token_id.to_string(), value: value.clone() })?,
+        None => levels.first().map_or(1, |x| x.level)
+    };
+    Ok(WolfProgress { xp, level })
+}
+
+/// Returns a wolf's XP and level from the contract's ledger. Wolves the ledger
+/// doesn't know yet start from the progress in their metadata
+fn current_progress(
+    tracked: Option<WolfProgress>,
+    ext: Option<&Extension>,
+    token_id: &str,
+    state: &State,
+    levels: &[Level]
+) -> Result<WolfProgress, ContractError> {
+    match tracked {
+        Some(progress) => Ok(progress),
+        None => metadata_progress(ext.unwrap_or(&Extension::default()), token_id, state, levels)
+    }
+}
+
+/// Adds XP to a wolf, levels it up and returns its level before and after
+fn add_xp(
+    progress: &mut WolfProgress,
+    xp: i32,
+    levels: &[Level],
+    level_cap: i32
+) -> (i32, i32) {
+    let current_lvl = progress.level;
+    progress.xp = progress.xp.saturating_add(xp);
+    progress.level = next_level(levels, progress.xp, current_lvl, level_cap);
+    (current_lvl, progress.level)
+}
+
+/// Writes a wolf's XP and level to its metadata, adding the traits when missing
+fn write_progress(
+    ext: &mut Extension,
+    progress: &WolfProgress,
+    state: &State
+) {
+    grant_trait(ext, &state.xp_trait, progress.xp.to_string());
+    grant_trait(ext, &state.level_trait, progress.level.to_string());
+}
+
+/// Returns the value of a metadata attribute
+fn find_trait(
+    ext: &Extension,
+    trait_type: &str
+) -> Option<String> {
+    ext.attributes.as_ref()
+        .and_then(|attrs| attrs.iter().find(|x| x.trait_type.as_deref() == Some(trait_type)))
+        .map(|x| x.value.clone())
+}
+
+/// Sets a metadata attribute, adding it when the token doesn't have it yet
+fn grant_trait(
+    ext: &mut Extension,
+    trait_type: &str,
+    value: String
+) {
+    let attrs = ext.attributes.get_or_insert_with(Vec::new);
+    match attrs.iter_mut().find(|x| x.trait_type.as_deref() == Some(trait_type)) {
+        Some(attr) => attr.value = value,
+        None => attrs.push(Trait {
+            display_type: None,
+            trait_type: Some(trait_type.to_string()),
+            value,
+            max_value: None
+        })
+    }
+}
+
+/// Returns the SHILL a loot drop pays out of the contract's balance
+fn shill_loot(
+    drop: &LootDrop,
+    state: &State
+) -> Uint128 {
+    match drop {
+        LootDrop::Snip20 { contract, amount } if contract.address == state.shill_contract.address => *amount,
+        _ => Uint128::from(0u32)
+    }
+}
+
+/// Returns the message handing a loot drop to the wolf's owner, trait drops
+/// are applied to the wolf's metadata instead
+fn loot_msg(
+    drop: &LootDrop,
+    recipient: &Addr
+) -> StdResult<Option<CosmosMsg>> {
+    let msg = match drop {
+        LootDrop::Snip20 { contract, amount } => transfer_msg(
+            recipient.to_string(),
+            *amount,
+            None,
+            None,
+            BLOCK_SIZE,
+            contract.code_hash.clone(),
+            contract.address.to_string()
+        )?,
+        LootDrop::Snip721 { contract, token_ids } => batch_transfer_nft_msg(
+            vec![Transfer {
+                recipient: recipient.to_string(),
+                token_ids: token_ids.clone(),
+                memo: None
+            }],
+            None,
+            BLOCK_SIZE,
+            contract.code_hash.clone(),
+            contract.address.to_string()
+        )?,
+        LootDrop::MintNft { contract, public_metadata } => mint_nft_msg(
+            None,
+            Some(recipient.to_string()),
+            public_metadata.clone(),
+            None,
+            None,
+            None,
+            BLOCK_SIZE,
+            contract.code_hash.clone(),
+            contract.address.to_string()
+        )?,
+        LootDrop::Trait { .. } => return Ok(None)
+    };
+    Ok(Some(msg))
+}
+
+#[entry_point]
+pub fn migrate(
+    deps: DepsMut,
+    _env: Env,
+    msg: MigrateMsg
+) -> StdResult<Response> {
+    // contracts instantiated before versioning was added don't have a stored version
+    let version = CONTRACT_VERSION_ITEM.may_load(deps.storage)?.unwrap_or(1);
+    if version > CONTRACT_VERSION {
+        return Err(StdError::generic_err(format!(
+            "Cannot migrate from contract version {} to older version {}",
+            version, CONTRACT_VERSION
+        )));
+    }
+
+    // run every upgrade step between the stored version and this code, in order
+    if version < 2 {
+        upgrade_v1_to_v2(deps.storage, deps.api, msg.entropy)?;
+    }
+
+    CONTRACT_VERSION_ITEM.save(deps.storage, &CONTRACT_VERSION)?;
+
+    // the token contracts call back with the code hash they were registered with,
+    // which changes with every migration. Version 1 never registered for SHILL
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    let mut msgs: Vec<CosmosMsg> = Vec::new();
+    for collection in state.collections.iter() {
+        msgs.push(register_receive_nft_msg(
+            _env.contract.code_hash.clone(),
+            Some(true),
+            None,
+            BLOCK_SIZE,
+            collection.contract.code_hash.clone(),
+            collection.contract.address.to_string(),
+        )?);
+    }
+    for token in state.fee_tokens.iter() {
+        msgs.push(register_receive_msg(
+            _env.contract.code_hash.clone(),
+            None,
+            BLOCK_SIZE,
+            token.code_hash.clone(),
+            token.address.to_string(),
+        )?);
+    }
+
+    deps.api.debug(&format!("Contract migrated from version {} to {}", version, CONTRACT_VERSION));
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attribute("previous_version", version.to_string())
+        .add_attribute("contract_version", CONTRACT_VERSION.to_string())
+    )
+}
+
+#[entry_point]
+pub fn query(
+    deps: Deps,
+    _env: Env,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    match msg { 
+        QueryMsg::GetQuests { start_page, page_size } => to_binary(&query_quests(deps, start_page, page_size)?),
+        QueryMsg::GetQuest { quest_id } => to_binary(&query_quest(deps, quest_id)?),
+        QueryMsg::GetCollections {} => to_binary(&query_collections(deps)?),
+        QueryMsg::GetLevels {} => to_binary(&query_levels(deps)?),
+        QueryMsg::GetParty { party_id } => to_binary(&query_party(deps, party_id)?),
+        QueryMsg::GetParties { start_page, page_size } => to_binary(&query_parties(deps, start_page, page_size)?),
+        QueryMsg::GetState {viewer} => to_binary(&query_state(deps, viewer)?),
+        QueryMsg::GetUserStakedNfts {permit} => query_user(deps, permit_querier(deps, permit)?, UserQuery::GetUserStakedNfts {}),
+        QueryMsg::GetUserEntryFee { permit, quest_id } => query_user(deps, permit_querier(deps, permit)?, UserQuery::GetUserEntryFee { quest_id }),
+        QueryMsg::GetSolvency { viewer } => to_binary(&query_solvency(deps, _env, viewer)?),
+        QueryMsg::GetWolfProgress { collection, token_ids } => to_binary(&query_wolf_progress(deps, collection, token_ids)?),
+        QueryMsg::GetTokenStatus { token_id, collection } => to_binary(&query_token_status(deps, token_id, collection)?),
+        QueryMsg::GetQuestParticipants { quest_id, page, page_size } => to_binary(&query_quest_participants(deps, quest_id, page, page_size)?),
+        QueryMsg::GetNumUserStakedNftHistory { permit } => query_user(deps, permit_querier(deps, permit)?, UserQuery::GetNumUserStakedNftHistory {}),
+        QueryMsg::GetUserStakedNftHistory {permit, start_page, page_size} => query_user(deps, permit_querier(deps, permit)?, UserQuery::GetUserStakedNftHistory { start_page, page_size }),
+        QueryMsg::WithPermit { permit, query } => query_user(deps, permit_querier(deps, permit)?, query),
+        QueryMsg::WithViewingKey { viewer, query } => query_user(deps, viewing_key_querier(deps, viewer)?, query),
+        QueryMsg::GetNumStakedNftKeys { viewer } => to_binary(&query_num_staked_keys(deps, viewer)?),
+        QueryMsg::GetStakedNfts { viewer, start_page, page_size } => to_binary(&query_staked_nfts(deps, viewer, start_page, page_size)?)
+       
+        
+    }
+}
+ 
+fn query_quests(
+    deps: Deps,
+    start_page: u32,
+    page_size: u32
+) -> StdResult<QuestResponse> {
+ 
+    let quests = quests_store().paging(deps.storage, start_page, page_size)?;
+    let total = quests_store().get_len(deps.storage)?;
+    Ok(QuestResponse { quests: quests.into_iter().map(|(_, quest)| quest).collect(), total })
+}
+
+fn query_quest(
+    deps: Deps,
+    quest_id: i32
+) -> StdResult<Quest> {
+    quests_store().get(deps.storage, &quest_id).ok_or_else(|| StdError::not_found("Quest"))
+}
+
+
+fn query_collections(
+    deps: Deps,
+) -> StdResult<Vec<ContractInfo>> {
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    Ok(state.collections.into_iter().map(|x| x.contract).collect())
+}
+
+fn query_levels(
+    deps: Deps,
+) -> StdResult<LevelsResponse> {
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    let levels = LEVEL_ITEM.load(deps.storage)?;
+    Ok(LevelsResponse { levels, level_cap: state.level_cap })
+}
+
+fn query_wolf_progress(
+    deps: Deps,
+    collection: Option<Addr>,
+    token_ids: Vec<String>
+) -> StdResult<Vec<WolfProgressResponse>> {
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    let collection = resolve_collection(&state, collection).map_err(|e| StdError::generic_err(e.to_string()))?;
+    let progress_store = wolf_progress_store(&collection.contract.address);
+    Ok(token_ids.into_iter().map(|token_id| WolfProgressResponse {
+        progress: progress_store.get(deps.storage, &token_id),
+        token_id
+    }).collect())
+}
+
+fn query_quest_participants(
+    deps: Deps,
+    quest_id: i32,
+    page: u32,
+    page_size: u32
+) -> StdResult<QuestParticipantsResponse> {
+    let quest = quests_store().get(deps.storage, &quest_id).ok_or_else(|| StdError::not_found("Quest"))?;
+    let participants = quest_participants_store(quest_id).paging(deps.storage, page, page_size)?;
+    let participants = participants.into_iter()
+        .filter_map(|((collection, token_id), _)| token_index_store(&collection).get(deps.storage, &token_id))
+        .collect();
+    Ok(QuestParticipantsResponse {
+        participants,
+        active: quest.wolves_on_the_hunt,
+        completed: quest.wolves_completed
+    })
+}
+
+fn query_token_status(
+    deps: Deps,
+    token_id: String,
+    collection: Option<Addr>
+) -> StdResult<TokenStatusResponse> {
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    let collection = resolve_collection(&state, collection).map_err(|e| StdError::generic_err(e.to_string()))?;
+    let nft = token_index_store(&collection.contract.address).get(deps.storage, &token_id);
+    let claimable_at = match &nft {
+        Some(nft) => match (nft.staked_date, quests_store().get(deps.storage, &nft.quest_id)) {
+            (Some(staked_date), Some(quest)) => Some(add_seconds(staked_date, quest.duration_in_staking)?),
+            _ => None
+        },
+        None => None
+    };
+    Ok(TokenStatusResponse {
+        token_id,
+        on_quest: nft.is_some(),
+        quest_id: nft.as_ref().map(|x| x.quest_id),
+        party_id: nft.as_ref().and_then(|x| x.party_id),
+        staked_date: nft.as_ref().and_then(|x| x.staked_date),
+        claimable_at
+    })
+}
+
+fn query_party(
+    deps: Deps,
+    party_id: u32
+) -> StdResult<Party> {
+    parties_store().get(deps.storage, &party_id).ok_or_else(|| StdError::not_found("Party"))
+}
+
+fn query_parties(
+    deps: Deps,
+    start_page: u32,
+    page_size: u32
+) -> StdResult<Vec<Party>> {
+    let parties = parties_store().paging(deps.storage, start_page, page_size)?;
+    Ok(parties.into_iter().map(|(_, party)| party).collect())
+}
+
+fn query_state(
+    deps: Deps,
+    viewer: ViewerInfo
+) -> StdResult<State> {
+    check_admin_key(deps, viewer.clone())?;
+
+    let mut state = CONFIG_ITEM.load(deps.storage)?;  
+    // only the owner gets to see the keys this contract set on other contracts
+    if viewer.address != state.owner.as_str() {
+        state.shill_viewing_key = None;
+        for collection in state.collections.iter_mut() {
+            collection.viewing_key = String::new();
+        }
+    }
+
+    Ok(state)
+}
+ 
+/// Answers a query about the wolves of an already authenticated user
+fn query_user(
+    deps: Deps,
+    user_raw: CanonicalAddr,
+    query: UserQuery
+) -> StdResult<Binary> {
+    match query {
+        UserQuery::GetUserStakedNfts {} => to_binary(&query_user_staked_nfts(deps, &user_raw)?),
+        UserQuery::GetUserStakedNftHistory { start_page, page_size } => to_binary(&user_staked_nft_history(deps, &user_raw, start_page, page_size)?),
+        UserQuery::GetNumUserStakedNftHistory {} => to_binary(&num_user_staked_nft_history(deps, &user_raw)?),
+        UserQuery::GetUserEntryFee { quest_id } => to_binary(&query_user_entry_fee(deps, &user_raw, quest_id)?),
+        UserQuery::GetPendingLoot {} => to_binary(&pending_loot_store().get(deps.storage, &user_raw).unwrap_or_default())
+    }
+}
+
+fn query_user_staked_nfts(
+    deps: Deps, 
+    user_raw: &CanonicalAddr
+) -> StdResult<Vec<Token>> { 
+    let staked_nfts = staked_nfts_store().get(deps.storage, user_raw).unwrap_or_default();
+ 
+    Ok(staked_nfts)
+}
+
+fn query_solvency(
+    deps: Deps,
+    env: Env,
+    viewer: ViewerInfo
+) -> StdResult<SolvencyResponse> {
+    check_admin_key(deps, viewer)?;
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    solvency(deps, &env, &state)
+}
+
+fn query_user_entry_fee(
+    deps: Deps,
+    user_raw: &CanonicalAddr,
+    quest_id: i32
+) -> StdResult<EntryFeeEscrow> {
+    Ok(entry_fee_store(quest_id).get(deps.storage, user_raw).unwrap_or_default())
+}
+
+fn query_num_staked_keys(
+    deps: Deps, 
+    viewer: ViewerInfo
+) -> StdResult<u32> {
+    check_admin_key(deps, viewer)?;
+    let num_staked_keys = staked_nfts_store().get_len(deps.storage)?;
+
+    Ok(num_staked_keys)
+}
+
+fn query_staked_nfts(
+    deps: Deps, 
+    viewer: ViewerInfo,
+    start_page: u32, 
+    page_size: u32
+) -> StdResult<Vec<(CanonicalAddr,Vec<Token>)>> {
+    check_admin_key(deps, viewer)?; 
+    let staked_nfts = staked_nfts_store().paging(deps.storage, start_page, page_size)?;
+    Ok(staked_nfts)
+}
+
+fn user_staked_nft_history(
+    deps: Deps,
+    user_raw: &CanonicalAddr,
+    start_page: u32,
+    page_size: u32
+) -> StdResult<Vec<HistoryToken>> {
+    let history = staked_history_store(user_raw).paging(deps.storage, start_page, page_size)?;
+    Ok(history)
+}
+
+fn num_user_staked_nft_history(
+    deps: Deps,
+    user_raw: &CanonicalAddr
+) -> StdResult<u32> {
+    staked_history_store(user_raw).get_len(deps.storage)
+}
+
+/// Hashes a viewing key the way SetViewingKey stores it
+fn hash_viewing_key(key: String) -> String {
+    let prng_seed: Vec<u8> = sha_256(base64::encode(key).as_bytes()).to_vec();
+    base64::encode(&prng_seed)
+}
+
+/// Checks the viewing key of the owner or of an address holding any role
+fn check_admin_key(deps: Deps, viewer: ViewerInfo) -> StdResult<()> {
+    let state = CONFIG_ITEM.load(deps.storage)?;
+    if !state.is_admin(&Addr::unchecked(viewer.address.clone())) {
+        return Err(StdError::generic_err(
+            "Wrong viewing key for this address or viewing key not set",
+        )); 
+    }
+
+    // the owner's key used to be kept on its own
+    if let Some(admin_viewing_key) = ADMIN_VIEWING_KEY_ITEM.may_load(deps.storage)? {
+        let vk = hash_viewing_key(viewer.viewing_key.clone());
+        if admin_viewing_key.address == state.owner.as_str() && viewer.address == admin_viewing_key.address
+            && ct_slice_compare(vk.as_bytes(), admin_viewing_key.viewing_key.as_bytes()) {
+            return Ok(());
+        }
+    }
+
+    viewing_key_querier(deps, viewer)?;
+    Ok(())
+}
+
+/// Returns the user a viewing key set with SetViewingKey belongs to
+fn viewing_key_querier(
+    deps: Deps,
+    viewer: ViewerInfo
+) -> StdResult<CanonicalAddr> {
+    let user_raw = deps.api.addr_canonicalize(&viewer.address)?;
+    let vk = hash_viewing_key(viewer.viewing_key);
+    match viewing_key_store().get(deps.storage, &user_raw) {
+        Some(stored) if ct_slice_compare(vk.as_bytes(), stored.viewing_key.as_bytes()) => Ok(user_raw),
+        _ => Err(StdError::generic_err(
+            "Wrong viewing key for this address or viewing key not set",
+        ))
+    }
+}
+
+/// Returns the user who signed a query permit
+fn permit_querier(
+    deps: Deps,
+    permit: Permit
+) -> StdResult<CanonicalAddr> {
+    let (user_raw, _) = get_querier(deps, permit)?;
+    Ok(user_raw)
+}
+
+fn get_querier(
+    deps: Deps,
+    permit: Permit,
+) -> StdResult<(CanonicalAddr, Option<CanonicalAddr>)> {
+    let me_raw: CanonicalAddr = MY_ADDRESS_ITEM.load(deps.storage)?;
+    let my_address = deps.api.addr_humanize(&me_raw)?;
+    let querier = deps.api.addr_canonicalize(&validate(
+        deps,
+        PREFIX_REVOKED_PERMITS,
+        &permit,
+        my_address.to_string(),
+        None
+    )?)?;
+    if !permit.check_permission(&secret_toolkit::permit::TokenPermissions::Owner) {
+        return Err(StdError::generic_err(format!(
+            "Owner permission is required for Stashh minter queries, got permissions {:?}",
+            permit.params.permissions
+        )));
+    }
+    Ok((querier, Some(me_raw)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockQuerier};
+    use cosmwasm_std::{Api, ContractResult, SystemResult};
+    use crate::msg::{AbandonPolicy, BonusRule, OutcomeRules, LootTable, LootEntry, EntryFee, MatchMode, TraitCondition, TraitComparison};
+    use cosmwasm_std::from_binary;
+    const NFT_CONTRACT: &str = "wolfpacknft";
+
+    fn init_with_quest(deps: DepsMut) {
+        let msg = InstantiateMsg {
+            entropy: "wolfpack".to_string(),
+            entropy_shill: "shill".to_string(),
+            quest_contract: ContractInfo { address: Addr::unchecked(NFT_CONTRACT), code_hash: "nft_hash".to_string() },
+            levels: vec![Level { level: 1, xp_needed: 0 }, Level { level: 2, xp_needed: 100 }],
+            level_cap: 2,
+            shill_contract: ContractInfo { address: Addr::unchecked("shilltoken"), code_hash: "shill_hash".to_string() }
+        };
+        instantiate(deps, mock_env(), mock_info("creator", &[]), msg).unwrap();
+    }
+
+    #[test]
+    fn proper_initialization() {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg {
+            entropy: "wolfpack".to_string(),
+            entropy_shill: "shill".to_string(),
+            quest_contract: ContractInfo { address: Addr::unchecked(NFT_CONTRACT), code_hash: "nft_hash".to_string() },
+            levels: vec![Level { level: 1, xp_needed: 0 }, Level { level: 2, xp_needed: 100 }],
+            level_cap: 2,
+            shill_contract: ContractInfo { address: Addr::unchecked("shilltoken"), code_hash: "shill_hash".to_string() }
+        };
+        let res = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        // receive and viewing key registrations with the collection and SHILL
+        assert_eq!(4, res.messages.len());
+
+        let res: LevelsResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetLevels {}).unwrap()).unwrap();
+        assert_eq!(2, res.levels.len());
+        assert_eq!(2, res.level_cap);
+        let collections: Vec<ContractInfo> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetCollections {}).unwrap()).unwrap();
+        assert_eq!(Addr::unchecked(NFT_CONTRACT), collections[0].address);
+    }
+
+    fn test_quest(quest_id: i32) -> Quest {
+        Quest { quest_id, start_time: mock_env().block.time.seconds(), ..Quest::default() }
+    }
+
+    /// Starts a quest with a reward pool big enough for every test
+    fn start_quest(mut deps: DepsMut, quest: Quest) {
+        let quest_id = quest.quest_id;
+        execute(deps.branch(), mock_env(), mock_info("creator", &[]), ExecuteMsg::StartQuest { quest }).unwrap();
+        execute(deps, mock_env(), mock_info("shilltoken", &[]), fund_msg(quest_id, 1000)).unwrap();
+    }
+
+    fn fund_msg(quest_id: i32, amount: u32) -> ExecuteMsg {
+        ExecuteMsg::Receive {
+            sender: Addr::unchecked("creator"),
+            from: Addr::unchecked("creator"),
+            amount: Uint128::from(amount),
+            memo: None,
+            msg: Some(to_binary(&ReceiveMsg::FundQuest { quest_id }).unwrap())
+        }
+    }
+
+    fn receive_msg(from: &str, quest_id: i32) -> ExecuteMsg {
+        ExecuteMsg::BatchReceiveNft {
+            from: Addr::unchecked(from),
+            token_ids: vec!["1".to_string()],
+            msg: Some(to_binary(&QuestMsg { quest_id, party_id: None }).unwrap())
+        }
+    }
+
+    #[test]
+    fn batch_receive_rejects_forged_callbacks() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), test_quest(1));
+
+        // a random contract pretending to be the NFT contract
+        let res = execute(deps.as_mut(), mock_env(), mock_info("forger", &[]), receive_msg("wolfowner", 1));
+        assert_eq!(res.unwrap_err(), ContractError::UnauthorizedNftContract { sender: "forger".to_string() });
+
+        // the wallet itself calling the receiver directly
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), receive_msg("wolfowner", 1));
+        assert_eq!(res.unwrap_err(), ContractError::UnauthorizedNftContract { sender: "wolfowner".to_string() });
+
+        let owner_raw = deps.api.addr_canonicalize("wolfowner").unwrap();
+        assert!(staked_nfts_store().get(&deps.storage, &owner_raw).is_none());
+        assert_eq!(0, quests_store().get(&deps.storage, &1).unwrap().wolves_on_the_hunt);
+    }
+
+    #[test]
+    fn batch_receive_accepts_nft_contract() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), test_quest(1));
+
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+
+        let owner_raw = deps.api.addr_canonicalize("wolfowner").unwrap();
+        let staked = staked_nfts_store().get(&deps.storage, &owner_raw).unwrap();
+        assert_eq!(1, staked.len());
+        assert_eq!("1", staked[0].token_id);
+        assert_eq!(Addr::unchecked("wolfowner"), staked[0].owner);
+    }
+
+    #[test]
+    fn wolves_have_to_meet_quest_requirements() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let mut quest = test_quest(1);
+        quest.min_level = Some(2);
+        quest.max_level = Some(3);
+        start_quest(deps.as_mut(), quest);
+        let mut quest = test_quest(2);
+        quest.required_traits = vec![TraitCondition { trait_type: "Pelt".to_string(), comparison: TraitComparison::Eq, value: "Rare".to_string() }];
+        start_quest(deps.as_mut(), quest);
+
+        mock_wolf_metadata(&mut deps.querier, 0, 1);
+        let res = execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1));
+        assert_eq!(res.unwrap_err(), ContractError::LevelTooLow { token_id: "1".to_string(), level: 1, min_level: 2 });
+        let res = execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 2));
+        assert_eq!(res.unwrap_err(), ContractError::RequirementNotMet { token_id: "1".to_string(), trait_type: "Pelt".to_string() });
+
+        mock_wolf_metadata(&mut deps.querier, 500, 4);
+        let res = execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1));
+        assert_eq!(res.unwrap_err(), ContractError::LevelTooHigh { token_id: "1".to_string(), level: 4, max_level: 3 });
+
+        mock_wolf_metadata(&mut deps.querier, 100, 2);
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+    }
+
+    #[test]
+    fn admin_manages_the_level_table() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let level = |level: i32, xp_needed: i32| Level { level, xp_needed };
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), ExecuteMsg::AddLevels { levels: vec![level(3, 250)] });
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::AddLevels { levels: vec![level(4, 250)] });
+        assert_eq!(res.unwrap_err(), ContractError::NonContiguousLevel { level: 4 });
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::AddLevels { levels: vec![level(3, 100)] });
+        assert_eq!(res.unwrap_err(), ContractError::XpNotIncreasing { level: 3 });
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::AddLevels { levels: vec![level(3, 250)] }).unwrap();
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::SetLevelCap { level_cap: 4 });
+        assert_eq!(res.unwrap_err(), ContractError::InvalidLevelCap { level_cap: 4 });
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::SetLevelCap { level_cap: 3 }).unwrap();
+
+        // the cap can't be dropped from the table
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::SetLevels { levels: vec![level(1, 0)] });
+        assert_eq!(res.unwrap_err(), ContractError::InvalidLevelCap { level_cap: 3 });
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::SetLevels { levels: vec![] });
+        assert_eq!(res.unwrap_err(), ContractError::NoLevels {});
+
+        let res = query_levels(deps.as_ref()).unwrap();
+        assert_eq!(vec![level(1, 0), level(2, 100), level(3, 250)], res.levels);
+        assert_eq!(3, res.level_cap);
+    }
+
+    #[test]
+    fn cancel_quest_returns_staked_wolves() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), test_quest(1));
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), ExecuteMsg::CancelQuest { quest_id: 1 });
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::CancelQuest { quest_id: 1 }).unwrap();
+        // the wolf and the quest's funding are sent back
+        assert_eq!(2, res.messages.len());
+        assert!(res.attributes.iter().any(|a| a.key == "returned_wolves" && a.value == "1"));
+        assert!(res.attributes.iter().any(|a| a.key == "returned_reward_pool" && a.value == "1000"));
+
+        let owner_raw = deps.api.addr_canonicalize("wolfowner").unwrap();
+        assert!(staked_nfts_store().get(&deps.storage, &owner_raw).unwrap().is_empty());
+        assert!(quests_store().get(&deps.storage, &1).is_none());
+    }
+
+    #[test]
+    fn added_collection_can_join_quests() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let pups = ContractInfo { address: Addr::unchecked("wolfpups"), code_hash: "pups_hash".to_string() };
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), ExecuteMsg::AddCollection { contract: pups.clone() });
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        let mut quest = test_quest(1);
+        quest.collections = vec![Addr::unchecked("wolfpups")];
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::StartQuest { quest: quest.clone() });
+        assert_eq!(res.unwrap_err(), ContractError::UnknownCollection { collection: "wolfpups".to_string() });
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::AddCollection { contract: pups.clone() }).unwrap();
+        // register receive and set viewing key on the new collection
+        assert_eq!(2, res.messages.len());
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::AddCollection { contract: pups });
+        assert_eq!(res.unwrap_err(), ContractError::CollectionExists { collection: "wolfpups".to_string() });
+
+        start_quest(deps.as_mut(), quest);
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1));
+        assert_eq!(res.unwrap_err(), ContractError::CollectionNotAccepted { quest_id: 1, collection: NFT_CONTRACT.to_string() });
+
+        execute(deps.as_mut(), mock_env(), mock_info("wolfpups", &[]), receive_msg("wolfowner", 1)).unwrap();
+        let owner_raw = deps.api.addr_canonicalize("wolfowner").unwrap();
+        let staked = staked_nfts_store().get(&deps.storage, &owner_raw).unwrap();
+        assert_eq!(Addr::unchecked("wolfpups"), staked[0].collection);
+    }
+
+    #[test]
+    fn joins_reserve_rewards_from_the_pool() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let mut quest = test_quest(1);
+        quest.bonus_rules = vec![BonusRule { conditions: vec![], match_mode: MatchMode::Any, shill_bonus: Uint128::from(5u32) }];
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::StartQuest { quest }).unwrap();
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1));
+        assert_eq!(res.unwrap_err(), ContractError::InsufficientRewardPool {
+            quest_id: 1,
+            required: Uint128::from(15u32),
+            available: Uint128::from(0u32)
+        });
+        let res = execute(deps.as_mut(), mock_env(), mock_info("gems", &[]), fund_msg(1, 20));
+        assert_eq!(res.unwrap_err(), ContractError::UnknownFeeToken { token: "gems".to_string() });
+
+        execute(deps.as_mut(), mock_env(), mock_info("shilltoken", &[]), fund_msg(1, 20)).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        let quest = quests_store().get(&deps.storage, &1).unwrap();
+        assert_eq!(Uint128::from(5u32), quest.reward_pool);
+        assert_eq!(Uint128::from(15u32), quest.reserved);
+        let funds = FUNDS_ITEM.load(&deps.storage).unwrap();
+        assert_eq!((Uint128::from(5u32), Uint128::from(15u32)), (funds.reward_pools, funds.reserved));
+
+        // the rule without conditions never matches, the unpaid bonus goes back to the pool
+        mock_wolf_metadata(&mut deps.querier, 0, 1);
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string()], collection: None, entropy: None };
+        execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim).unwrap();
+        let quest = quests_store().get(&deps.storage, &1).unwrap();
+        assert_eq!(Uint128::from(10u32), quest.reward_pool);
+        assert_eq!(Uint128::from(0u32), quest.reserved);
+        let funds = FUNDS_ITEM.load(&deps.storage).unwrap();
+        assert_eq!((Uint128::from(10u32), Uint128::from(0u32)), (funds.reward_pools, funds.reserved));
+    }
+
+    #[test]
+    fn shill_owed_to_players_cant_be_withdrawn() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), test_quest(1));
+        deps.querier.update_wasm(|_| SystemResult::Ok(ContractResult::Ok(Binary::from(br#"{"balance":{"amount":"1200"}}"#.to_vec()))));
+
+        let withdraw = |amount: u32| ExecuteMsg::SendShillBack { amount: Uint128::from(amount), address: Addr::unchecked("creator") };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), withdraw(201));
+        assert_eq!(res.unwrap_err(), ContractError::InsufficientFreeBalance { available: Uint128::from(200u32) });
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), withdraw(200)).unwrap();
+
+        let state = CONFIG_ITEM.load(&deps.storage).unwrap();
+        let funds = solvency(deps.as_ref(), &mock_env(), &state).unwrap();
+        assert_eq!(Uint128::from(1000u32), funds.reward_pools);
+        assert!(funds.solvent);
+    }
+
+    fn pay_fee(amount: u32) -> ExecuteMsg {
+        ExecuteMsg::Receive {
+            sender: Addr::unchecked("wolfowner"),
+            from: Addr::unchecked("wolfowner"),
+            amount: Uint128::from(amount),
+            memo: None,
+            msg: Some(to_binary(&ReceiveMsg::PayEntryFee { quest_id: 1 }).unwrap())
+        }
+    }
+
+    fn fee_quest() -> Quest {
+        let mut quest = test_quest(1);
+        quest.entry_fee = Some(EntryFee {
+            token: Addr::unchecked("shilltoken"),
+            amount: Uint128::from(5u32),
+            destination: FeeDestination::Treasury { address: Addr::unchecked("treasury") }
+        });
+        quest
+    }
+
+    #[test]
+    fn entry_fees_are_paid_before_joining() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), fee_quest());
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1));
+        assert_eq!(res.unwrap_err(), ContractError::EntryFeeNotPaid {
+            quest_id: 1,
+            required: Uint128::from(5u32),
+            paid: Uint128::from(0u32)
+        });
+        let res = execute(deps.as_mut(), mock_env(), mock_info("forgedtoken", &[]), pay_fee(5));
+        assert_eq!(res.unwrap_err(), ContractError::UnknownFeeToken { token: "forgedtoken".to_string() });
+
+        execute(deps.as_mut(), mock_env(), mock_info("shilltoken", &[]), pay_fee(8)).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        let owner_raw = deps.api.addr_canonicalize("wolfowner").unwrap();
+        let escrow = entry_fee_store(1).get(&deps.storage, &owner_raw).unwrap();
+        assert_eq!(EntryFeeEscrow { paid: Uint128::from(3u32), locked: Uint128::from(5u32) }, escrow);
+        assert_eq!(Uint128::from(8u32), FUNDS_ITEM.load(&deps.storage).unwrap().entry_fees);
+
+        mock_wolf_metadata(&mut deps.querier, 0, 1);
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string()], collection: None, entropy: None };
+        let res = execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim).unwrap();
+        // metadata update, the fee sent to the treasury, the wolf going back and the SHILL reward
+        assert_eq!(4, res.messages.len());
+        let escrow = entry_fee_store(1).get(&deps.storage, &owner_raw).unwrap();
+        assert_eq!(Uint128::from(0u32), escrow.locked);
+
+        assert_eq!(Uint128::from(3u32), FUNDS_ITEM.load(&deps.storage).unwrap().entry_fees);
+
+        let res = execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), ExecuteMsg::WithdrawEntryFee { quest_id: 1 }).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "entry_fee_withdrawn" && a.value == "3"));
+        assert_eq!(Uint128::from(0u32), FUNDS_ITEM.load(&deps.storage).unwrap().entry_fees);
+    }
+
+    #[test]
+    fn wolves_missing_from_the_index_can_still_leave() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), test_quest(1));
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        token_index_store(&Addr::unchecked(NFT_CONTRACT)).remove(&mut deps.storage, &"1".to_string()).unwrap();
+
+        let send_back = ExecuteMsg::SendNftBack { token_id: "1".to_string(), owner: Addr::unchecked("wolfowner"), collection: None };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), send_back).unwrap();
+        assert_eq!(0, quests_store().get(&deps.storage, &1).unwrap().wolves_on_the_hunt);
+    }
+
+    #[test]
+    fn sent_back_wolves_unlock_their_entry_fee() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), fee_quest());
+        execute(deps.as_mut(), mock_env(), mock_info("shilltoken", &[]), pay_fee(5)).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+
+        let send_back = ExecuteMsg::SendNftBack { token_id: "1".to_string(), owner: Addr::unchecked("wolfowner"), collection: None };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), send_back).unwrap();
+        let owner_raw = deps.api.addr_canonicalize("wolfowner").unwrap();
+        let escrow = entry_fee_store(1).get(&deps.storage, &owner_raw).unwrap();
+        assert_eq!(EntryFeeEscrow { paid: Uint128::from(5u32), locked: Uint128::from(0u32) }, escrow);
+    }
+
+    #[test]
+    fn cancelled_quests_refund_entry_fees() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), fee_quest());
+        execute(deps.as_mut(), mock_env(), mock_info("shilltoken", &[]), pay_fee(8)).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::CancelQuest { quest_id: 1 }).unwrap();
+        // the wolf and the whole fee go back, the reward pool goes to the owner
+        assert_eq!(3, res.messages.len());
+        assert!(res.attributes.iter().any(|a| a.key == "refunded_entry_fees" && a.value == "1"));
+        let owner_raw = deps.api.addr_canonicalize("wolfowner").unwrap();
+        assert!(entry_fee_store(1).get(&deps.storage, &owner_raw).is_none());
+    }
+
+    fn party_msg(from: &str, token_id: &str, party_id: Option<u32>) -> ExecuteMsg {
+        ExecuteMsg::BatchReceiveNft {
+            from: Addr::unchecked(from),
+            token_ids: vec![token_id.to_string()],
+            msg: Some(to_binary(&QuestMsg { quest_id: 1, party_id }).unwrap())
+        }
+    }
+
+    #[test]
+    fn sent_back_wolves_leave_their_party() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let mut quest = test_quest(1);
+        quest.party_size = 2;
+        start_quest(deps.as_mut(), quest);
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), party_msg("leader", "1", None)).unwrap();
+
+        let send_back = ExecuteMsg::SendNftBack { token_id: "1".to_string(), owner: Addr::unchecked("leader"), collection: None };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), send_back).unwrap();
+        assert!(parties_store().get(&deps.storage, &1).is_none());
+    }
+
+    #[test]
+    fn party_hunts_once_full_and_splits_rewards() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let mut quest = test_quest(1);
+        quest.party_size = 2;
+        start_quest(deps.as_mut(), quest);
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), party_msg("leader", "1", None)).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "party_id" && a.value == "1"));
+        let res = execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), party_msg("leader", "2", Some(1)));
+        assert_eq!(res.unwrap_err(), ContractError::AlreadyInParty { party_id: 1 });
+
+        // the leader's wolf waits for the party to fill up
+        mock_wolf_metadata(&mut deps.querier, 0, 1);
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string()], collection: None, entropy: None };
+        let res = execute(deps.as_mut(), claim_env(), mock_info("leader", &[]), claim.clone());
+        assert_eq!(res.unwrap_err(), ContractError::PartyNotFormed { token_id: "1".to_string() });
+
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), party_msg("member", "3", Some(1))).unwrap();
+        let party = query_party(deps.as_ref(), 1).unwrap();
+        assert_eq!(vec![Addr::unchecked("leader"), Addr::unchecked("member")], party.members);
+        assert_eq!(Some(mock_env().block.time.seconds()), party.formed_date);
+        let res = execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), party_msg("late", "4", Some(1)));
+        assert_eq!(res.unwrap_err(), ContractError::PartyFull { party_id: 1 });
+
+        let res = execute(deps.as_mut(), claim_env(), mock_info("leader", &[]), claim).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "shill_amount" && a.value == "5"));
+        let leader_raw = deps.api.addr_canonicalize("leader").unwrap();
+        let history = user_staked_nft_history(deps.as_ref(), &leader_raw, 0, 10).unwrap();
+        assert_eq!(Some(1), history[0].party_id);
+        assert_eq!(25, history[0].xp_reward);
+    }
+
+    #[test]
+    fn leaving_an_unformed_party_is_free() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let mut quest = test_quest(1);
+        quest.party_size = 3;
+        quest.abandon_policy = AbandonPolicy { xp_percent: 0, shill_fee: Uint128::from(5u32) };
+        start_quest(deps.as_mut(), quest);
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), party_msg("leader", "1", None)).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), party_msg("member", "2", Some(1))).unwrap();
+
+        let abandon = ExecuteMsg::AbandonQuest { token_ids: vec!["1".to_string()], collection: None };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("leader", &[]), abandon).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "shill_fee" && a.value == "0"));
+        let party = query_party(deps.as_ref(), 1).unwrap();
+        assert_eq!(Addr::unchecked("member"), party.leader);
+        assert_eq!(vec![Addr::unchecked("member")], party.members);
+
+        let abandon = ExecuteMsg::AbandonQuest { token_ids: vec!["2".to_string()], collection: None };
+        execute(deps.as_mut(), mock_env(), mock_info("member", &[]), abandon).unwrap();
+        assert!(query_party(deps.as_ref(), 1).is_err());
+    }
+
+    #[test]
+    fn bonus_cap_can_be_cleared() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), test_quest(1));
+
+        let cap = QuestUpdate { max_bonus_reward: Some(Uint128::from(5u32)), ..QuestUpdate::default() };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::UpdateQuest { quest_id: 1, update: cap }).unwrap();
+        assert_eq!(Some(Uint128::from(5u32)), quests_store().get(&deps.storage, &1).unwrap().max_bonus_reward);
+
+        // leaving the cap out keeps it
+        let title = QuestUpdate { title: Some("Night hunt".to_string()), ..QuestUpdate::default() };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::UpdateQuest { quest_id: 1, update: title }).unwrap();
+        assert_eq!(Some(Uint128::from(5u32)), quests_store().get(&deps.storage, &1).unwrap().max_bonus_reward);
+
+        let clear = QuestUpdate { clear_max_bonus_reward: true, ..QuestUpdate::default() };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::UpdateQuest { quest_id: 1, update: clear }).unwrap();
+        assert_eq!(None, quests_store().get(&deps.storage, &1).unwrap().max_bonus_reward);
+    }
+
+    fn mock_wolf_metadata(querier: &mut MockQuerier, xp: i32, lvl: i32) {
+        let dossier = format!(
+            r#"{{"nft_dossier":{{"owner":null,"public_metadata":{{"token_uri":null,"extension":{{"attributes":[{{"trait_type":"XP","value":"{}"}},{{"trait_type":"LVL","value":"{}"}}]}}}},"private_metadata":null,"display_private_metadata_error":null,"owner_is_public":false,"public_ownership_expiration":null,"private_metadata_is_public":false,"private_metadata_is_public_expiration":null,"token_approvals":null,"inventory_approvals":null}}}}"#,
+            xp, lvl
+        );
+        querier.update_wasm(move |_| SystemResult::Ok(ContractResult::Ok(Binary::from(dossier.as_bytes()))));
+    }
+
+    fn claim_env() -> Env {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(200);
+        env
+    }
+
+    fn user_history(deps: Deps, address: &str, key: &str) -> Vec<HistoryToken> {
+        let msg = QueryMsg::WithViewingKey {
+            viewer: ViewerInfo { address: address.to_string(), viewing_key: key.to_string() },
+            query: UserQuery::GetUserStakedNftHistory { start_page: 0, page_size: 10 }
+        };
+        from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn claimed_wolves_show_up_in_history() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), test_quest(1));
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        mock_wolf_metadata(&mut deps.querier, 0, 1);
+
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string()], collection: None, entropy: None };
+        execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim).unwrap();
+
+        execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), ExecuteMsg::SetViewingKey { key: "howl".to_string() }).unwrap();
+        let num_query = QueryMsg::WithViewingKey {
+            viewer: ViewerInfo { address: "wolfowner".to_string(), viewing_key: "howl".to_string() },
+            query: UserQuery::GetNumUserStakedNftHistory {}
+        };
+        let num: u32 = from_binary(&query(deps.as_ref(), mock_env(), num_query).unwrap()).unwrap();
+        assert_eq!(1, num);
+        let history = user_history(deps.as_ref(), "wolfowner", "howl");
+        assert_eq!("1", history[0].token_id);
+        assert_eq!(1, history[0].quest_id);
+        assert_eq!(Some(claim_env().block.time.seconds()), history[0].claimed_date);
+    }
+
+    #[test]
+    fn history_records_rewards_per_wolf() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let mut quest = test_quest(1);
+        quest.num_of_nfts = 2;
+        start_quest(deps.as_mut(), quest);
+        let receive = ExecuteMsg::BatchReceiveNft {
+            from: Addr::unchecked("wolfowner"),
+            token_ids: vec!["1".to_string(), "2".to_string()],
+            msg: Some(to_binary(&QuestMsg { quest_id: 1, party_id: None }).unwrap())
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive).unwrap();
+        mock_wolf_metadata(&mut deps.querier, 0, 1);
+
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string(), "2".to_string()], collection: None, entropy: None };
+        let res = execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "shill_amount" && a.value == "20"));
+
+        execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), ExecuteMsg::SetViewingKey { key: "howl".to_string() }).unwrap();
+        let history = user_history(deps.as_ref(), "wolfowner", "howl");
+        assert_eq!(2, history.len());
+        for entry in history {
+            assert_eq!(Uint128::from(10u32), entry.reward_amount);
+            assert_eq!(Uint128::from(10u32), entry.base_reward);
+            assert_eq!(Uint128::from(0u32), entry.bonus_reward);
+            assert_eq!(50, entry.xp_reward);
+            assert_eq!(Some(1), entry.level);
+        }
+    }
+
+    #[test]
+    fn claims_roll_quest_outcomes() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let mut quest = test_quest(1);
+        quest.outcome_rules = Some(OutcomeRules {
+            success_chance: 0,
+            critical_chance: 0,
+            critical_multiplier: 2,
+            min_xp_percent: 100,
+            max_xp_percent: 100
+        });
+        let mut invalid = quest.clone();
+        invalid.outcome_rules.as_mut().unwrap().critical_multiplier = 0;
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::StartQuest { quest: invalid });
+        assert_eq!(res.unwrap_err(), ContractError::InvalidOutcomeRules { quest_id: 1 });
+        start_quest(deps.as_mut(), quest);
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        mock_wolf_metadata(&mut deps.querier, 0, 1);
+        let seed = PRNG_SEED_ITEM.load(&deps.storage).unwrap();
+
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string()], collection: None, entropy: Some("howl".to_string()) };
+        let res = execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "outcome_1" && a.value == "failure"));
+        assert!(res.attributes.iter().any(|a| a.key == "shill_amount" && a.value == "0"));
+        assert_ne!(seed, PRNG_SEED_ITEM.load(&deps.storage).unwrap());
+
+        let owner_raw = deps.api.addr_canonicalize("wolfowner").unwrap();
+        let history = user_staked_nft_history(deps.as_ref(), &owner_raw, 0, 10).unwrap();
+        assert_eq!(HuntOutcome::Failure, history[0].roll.as_ref().unwrap().outcome);
+        assert_eq!(0, history[0].xp_reward);
+    }
+
+    #[test]
+    fn claims_hand_out_loot() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let gems = ContractInfo { address: Addr::unchecked("gems"), code_hash: "gems_hash".to_string() };
+        let mut quest = test_quest(1);
+        quest.loot_tables = vec![
+            LootTable { drop_chance: 100, entries: vec![LootEntry { weight: 1, drop: LootDrop::Snip20 { contract: gems, amount: Uint128::from(3u32) } }] },
+            LootTable { drop_chance: 100, entries: vec![LootEntry { weight: 1, drop: LootDrop::Trait { trait_type: "Pelt".to_string(), value: "Rare".to_string() } }] }
+        ];
+        start_quest(deps.as_mut(), quest);
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        mock_wolf_metadata(&mut deps.querier, 0, 1);
+
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string()], collection: None, entropy: None };
+        let res = execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim).unwrap();
+        // metadata update, the wolf going back and the SHILL reward, the gems wait to be claimed
+        assert_eq!(3, res.messages.len());
+        assert!(res.attributes.iter().any(|a| a.key == "loot_1" && a.value == "2"));
+
+        let owner_raw = deps.api.addr_canonicalize("wolfowner").unwrap();
+        let history = user_staked_nft_history(deps.as_ref(), &owner_raw, 0, 10).unwrap();
+        assert_eq!(2, history[0].loot.len());
+
+        execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), ExecuteMsg::SetViewingKey { key: "howl".to_string() }).unwrap();
+        let pending_query = QueryMsg::WithViewingKey {
+            viewer: ViewerInfo { address: "wolfowner".to_string(), viewing_key: "howl".to_string() },
+            query: UserQuery::GetPendingLoot {}
+        };
+        let pending: Vec<LootDrop> = from_binary(&query(deps.as_ref(), mock_env(), pending_query.clone()).unwrap()).unwrap();
+        assert_eq!(1, pending.len());
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), ExecuteMsg::ClaimLoot { index: Some(1) });
+        assert_eq!(res.unwrap_err(), ContractError::NoPendingLoot {});
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), ExecuteMsg::ClaimLoot { index: Some(0) }).unwrap();
+        assert_eq!(1, res.messages.len());
+        let pending: Vec<LootDrop> = from_binary(&query(deps.as_ref(), mock_env(), pending_query).unwrap()).unwrap();
+        assert!(pending.is_empty());
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), ExecuteMsg::ClaimLoot { index: None });
+        assert_eq!(res.unwrap_err(), ContractError::NoPendingLoot {});
+    }
+
+    #[test]
+    fn unclaimed_shill_loot_is_owed_to_players() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let shill = ContractInfo { address: Addr::unchecked("shilltoken"), code_hash: "shill_hash".to_string() };
+        let mut quest = test_quest(1);
+        quest.loot_tables = vec![
+            LootTable { drop_chance: 100, entries: vec![LootEntry { weight: 1, drop: LootDrop::Snip20 { contract: shill, amount: Uint128::from(4u32) } }] }
+        ];
+        start_quest(deps.as_mut(), quest);
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        mock_wolf_metadata(&mut deps.querier, 0, 1);
+
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string()], collection: None, entropy: None };
+        execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim).unwrap();
+        assert_eq!(Uint128::from(4u32), FUNDS_ITEM.load(&deps.storage).unwrap().loot);
+
+        execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), ExecuteMsg::ClaimLoot { index: None }).unwrap();
+        assert_eq!(Uint128::from(0u32), FUNDS_ITEM.load(&deps.storage).unwrap().loot);
+    }
+
+    #[test]
+    fn granted_traits_are_added_or_updated() {
+        let mut ext = Extension::default();
+        grant_trait(&mut ext, "Pelt", "Rare".to_string());
+        grant_trait(&mut ext, "Pelt", "Epic".to_string());
+        let attrs = ext.attributes.unwrap();
+        assert_eq!(1, attrs.len());
+        assert_eq!("Epic", attrs[0].value);
+    }
+
+    #[test]
+    fn claims_emit_every_level_gained() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::AddLevels { levels: vec![Level { level: 3, xp_needed: 250 }] }).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::SetLevelCap { level_cap: 3 }).unwrap();
+        let mut quest = test_quest(1);
+        quest.xp_reward = 5000;
+        start_quest(deps.as_mut(), quest);
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        mock_wolf_metadata(&mut deps.querier, 0, 1);
+
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string()], collection: None, entropy: None };
+        let res = execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim).unwrap();
+        let gained: Vec<&str> = res.attributes.iter().filter(|a| a.key == "lvl_increase_1").map(|a| a.value.as_str()).collect();
+        assert_eq!(vec!["2", "3"], gained);
+
+        let owner_raw = deps.api.addr_canonicalize("wolfowner").unwrap();
+        let history = user_staked_nft_history(deps.as_ref(), &owner_raw, 0, 10).unwrap();
+        assert_eq!(Some(3), history[0].level);
+    }
+
+    #[test]
+    fn progress_traits_are_created_in_private_metadata() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let traits = ExecuteMsg::SetProgressTraits { xp_trait: "Experience".to_string(), level_trait: "Rank".to_string(), private: true };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), traits.clone());
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        let same = ExecuteMsg::SetProgressTraits { xp_trait: "XP".to_string(), level_trait: "XP".to_string(), private: false };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), same);
+        assert_eq!(res.unwrap_err(), ContractError::InvalidProgressTraits {});
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), traits).unwrap();
+
+        start_quest(deps.as_mut(), test_quest(1));
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        // a wolf that never went on a quest has no XP or level traits yet
+        let dossier = r#"{"nft_dossier":{"owner":null,"public_metadata":null,"private_metadata":null,"display_private_metadata_error":null,"owner_is_public":false,"public_ownership_expiration":null,"private_metadata_is_public":false,"private_metadata_is_public_expiration":null,"token_approvals":null,"inventory_approvals":null}}"#;
+        deps.querier.update_wasm(move |_| SystemResult::Ok(ContractResult::Ok(Binary::from(dossier.as_bytes()))));
+
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string()], collection: None, entropy: None };
+        let res = execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim).unwrap();
+        let set_metadata = res.messages.iter().find_map(|m| match &m.msg {
+            CosmosMsg::Wasm(cosmwasm_std::WasmMsg::Execute { msg, .. }) if String::from_utf8_lossy(msg.as_slice()).contains("set_metadata") =>
+                Some(String::from_utf8_lossy(msg.as_slice()).to_string()),
+            _ => None
+        }).unwrap();
+        assert!(set_metadata.contains(r#""public_metadata":null"#));
+        assert!(set_metadata.contains(r#""trait_type":"Experience""#));
+        assert!(set_metadata.contains(r#""trait_type":"Rank""#));
+
+        let owner_raw = deps.api.addr_canonicalize("wolfowner").unwrap();
+        let history = user_staked_nft_history(deps.as_ref(), &owner_raw, 0, 10).unwrap();
+        assert_eq!(Some(1), history[0].level);
+    }
+
+    fn wolf_progress(deps: Deps, token_id: &str) -> Option<WolfProgress> {
+        let query_msg = QueryMsg::GetWolfProgress { collection: None, token_ids: vec![token_id.to_string()] };
+        let res: Vec<WolfProgressResponse> = from_binary(&query(deps, mock_env(), query_msg).unwrap()).unwrap();
+        res[0].progress.clone()
+    }
+
+    #[test]
+    fn ledger_progress_survives_metadata_edits() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), test_quest(1));
+        assert_eq!(None, wolf_progress(deps.as_ref(), "1"));
+
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string()], collection: None, entropy: None };
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        mock_wolf_metadata(&mut deps.querier, 30, 1);
+        execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim.clone()).unwrap();
+        assert_eq!(Some(WolfProgress { xp: 80, level: 1 }), wolf_progress(deps.as_ref(), "1"));
+
+        // someone with metadata rights resets the wolf, the ledger keeps counting
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        mock_wolf_metadata(&mut deps.querier, 0, 1);
+        execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim).unwrap();
+        assert_eq!(Some(WolfProgress { xp: 130, level: 2 }), wolf_progress(deps.as_ref(), "1"));
+
+        let owner_raw = deps.api.addr_canonicalize("wolfowner").unwrap();
+        let history = user_staked_nft_history(deps.as_ref(), &owner_raw, 0, 10).unwrap();
+        assert_eq!(Some(2), history[1].level);
+
+        // requirements and bonus rules read the ledger's level, not the reset metadata
+        let level_two = || vec![TraitCondition { trait_type: "LVL".to_string(), comparison: TraitComparison::Gte, value: "2".to_string() }];
+        let mut quest = test_quest(2);
+        quest.required_traits = level_two();
+        quest.bonus_rules = vec![BonusRule { conditions: level_two(), match_mode: MatchMode::All, shill_bonus: Uint128::from(5u32) }];
+        start_quest(deps.as_mut(), quest);
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 2)).unwrap();
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string()], collection: None, entropy: None };
+        execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim).unwrap();
+        let history = user_staked_nft_history(deps.as_ref(), &owner_raw, 0, 10).unwrap();
+        assert_eq!(Uint128::from(5u32), history.iter().find(|x| x.quest_id == 2).unwrap().bonus_reward);
+    }
+
+    fn token_status(deps: Deps, token_id: &str) -> TokenStatusResponse {
+        let query_msg = QueryMsg::GetTokenStatus { token_id: token_id.to_string(), collection: None };
+        from_binary(&query(deps, mock_env(), query_msg).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn token_status_follows_the_wolf() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), test_quest(1));
+        assert!(!token_status(deps.as_ref(), "1").on_quest);
+
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        let status = token_status(deps.as_ref(), "1");
+        let staked_date = mock_env().block.time.seconds();
+        assert!(status.on_quest);
+        assert_eq!(Some(1), status.quest_id);
+        assert_eq!(Some(staked_date), status.staked_date);
+        assert_eq!(Some(staked_date + 100), status.claimable_at);
+
+        mock_wolf_metadata(&mut deps.querier, 0, 1);
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string()], collection: None, entropy: None };
+        execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim).unwrap();
+        assert!(!token_status(deps.as_ref(), "1").on_quest);
+
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        let send_back = ExecuteMsg::SendNftBack { token_id: "1".to_string(), owner: Addr::unchecked("wolfowner"), collection: None };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), send_back).unwrap();
+        assert_eq!(None, token_status(deps.as_ref(), "1").quest_id);
+    }
+
+    fn quest_participants(deps: Deps) -> QuestParticipantsResponse {
+        let query_msg = QueryMsg::GetQuestParticipants { quest_id: 1, page: 0, page_size: 10 };
+        from_binary(&query(deps, mock_env(), query_msg).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn participants_and_counters_follow_the_quest() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), test_quest(1));
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), party_msg("alpha", "1", None)).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), party_msg("beta", "2", None)).unwrap();
+
+        let res = quest_participants(deps.as_ref());
+        assert_eq!(2, res.active);
+        assert_eq!(0, res.completed);
+        let mut owners: Vec<String> = res.participants.iter().map(|x| x.owner.to_string()).collect();
+        owners.sort();
+        assert_eq!(vec!["alpha", "beta"], owners);
+
+        mock_wolf_metadata(&mut deps.querier, 0, 1);
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string()], collection: None, entropy: None };
+        execute(deps.as_mut(), claim_env(), mock_info("alpha", &[]), claim).unwrap();
+        let send_back = ExecuteMsg::SendNftBack { token_id: "2".to_string(), owner: Addr::unchecked("beta"), collection: None };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), send_back).unwrap();
+
+        let res = quest_participants(deps.as_ref());
+        assert!(res.participants.is_empty());
+        assert_eq!(0, res.active);
+        assert_eq!(1, res.completed);
+
+        // with every wolf gone the quest can be changed again
+        let quest = quests_store().get(&deps.storage, &1).unwrap();
+        assert_eq!(Uint128::from(0u32), quest.reserved);
+        let update = ExecuteMsg::UpdateQuest { quest_id: 1, update: QuestUpdate { title: Some("Night hunt".to_string()), ..QuestUpdate::default() } };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+    }
+
+    #[test]
+    fn viewing_key_reads_the_users_wolves() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), test_quest(1));
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+
+        let query_msg = |key: &str| QueryMsg::WithViewingKey {
+            viewer: ViewerInfo { address: "wolfowner".to_string(), viewing_key: key.to_string() },
+            query: UserQuery::GetUserStakedNfts {}
+        };
+        assert!(query(deps.as_ref(), mock_env(), query_msg("howl")).is_err());
+
+        execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), ExecuteMsg::SetViewingKey { key: "howl".to_string() }).unwrap();
+        let nfts: Vec<Token> = from_binary(&query(deps.as_ref(), mock_env(), query_msg("howl")).unwrap()).unwrap();
+        assert_eq!(1, nfts.len());
+        assert_eq!("1", nfts[0].token_id);
+        assert!(query(deps.as_ref(), mock_env(), query_msg("growl")).is_err());
+
+        // a key only opens the data of the address it was set for
+        let other = QueryMsg::WithViewingKey {
+            viewer: ViewerInfo { address: "creator".to_string(), viewing_key: "howl".to_string() },
+            query: UserQuery::GetNumUserStakedNftHistory {}
+        };
+        assert!(query(deps.as_ref(), mock_env(), other).is_err());
+    }
+
+    #[test]
+    fn created_viewing_key_is_returned_and_accepted() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), test_quest(1));
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), ExecuteMsg::CreateViewingKey { entropy: "moon".to_string() }).unwrap();
+        let answer: ExecuteAnswer = from_binary(&res.data.unwrap()).unwrap();
+        let ExecuteAnswer::CreateViewingKey { key: first } = answer;
+        let query_msg = QueryMsg::WithViewingKey {
+            viewer: ViewerInfo { address: "wolfowner".to_string(), viewing_key: first.clone() },
+            query: UserQuery::GetUserStakedNfts {}
+        };
+        let nfts: Vec<Token> = from_binary(&query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap();
+        assert_eq!(1, nfts.len());
+
+        // the seed moves on, the same entropy doesn't give the same key twice
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), ExecuteMsg::CreateViewingKey { entropy: "moon".to_string() }).unwrap();
+        let ExecuteAnswer::CreateViewingKey { key: second } = from_binary(&res.data.unwrap()).unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn revoked_permits_are_recorded_for_the_sender() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), ExecuteMsg::RevokePermit { permit_name: "wallet".to_string() }).unwrap();
+        assert!(RevokedPermits::is_permit_revoked(&deps.storage, PREFIX_REVOKED_PERMITS, "wolfowner", "wallet"));
+        assert!(!RevokedPermits::is_permit_revoked(&deps.storage, PREFIX_REVOKED_PERMITS, "creator", "wallet"));
+    }
+
+    #[test]
+    fn ownership_moves_only_once_accepted() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let accept = ExecuteMsg::AcceptOwnership {};
+        let res = execute(deps.as_mut(), mock_env(), mock_info("newowner", &[]), accept.clone());
+        assert_eq!(res.unwrap_err(), ContractError::NoPendingOwner {});
+        let propose = ExecuteMsg::ProposeOwner { address: Addr::unchecked("newowner") };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("newowner", &[]), propose.clone());
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), propose).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info("stranger", &[]), accept.clone());
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        // the current owner keeps its rights until the transfer is accepted
+        start_quest(deps.as_mut(), test_quest(1));
+        execute(deps.as_mut(), mock_env(), mock_info("newowner", &[]), accept).unwrap();
+
+        let state = CONFIG_ITEM.load(&deps.storage).unwrap();
+        assert_eq!(Addr::unchecked("newowner"), state.owner);
+        assert_eq!(None, state.pending_owner);
+        let close = ExecuteMsg::CloseQuest { quest_id: 1 };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), close.clone());
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info("newowner", &[]), close).unwrap();
+    }
+
+    #[test]
+    fn roles_limit_what_admins_can_do() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let grant = |address: &str, role: Role| ExecuteMsg::GrantRole { address: Addr::unchecked(address), role };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("manager", &[]), grant("manager", Role::QuestManager));
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), grant("manager", Role::QuestManager)).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), grant("operator", Role::Operator)).unwrap();
+
+        execute(deps.as_mut(), mock_env(), mock_info("manager", &[]), ExecuteMsg::StartQuest { quest: test_quest(2) }).unwrap();
+        let shill_back = ExecuteMsg::SendShillBack { amount: Uint128::from(1u32), address: Addr::unchecked("manager") };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("manager", &[]), shill_back);
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        let res = execute(deps.as_mut(), mock_env(), mock_info("operator", &[]), ExecuteMsg::CloseQuest { quest_id: 2 });
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+
+        start_quest(deps.as_mut(), test_quest(1));
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        let send_back = ExecuteMsg::SendNftBack { token_id: "1".to_string(), owner: Addr::unchecked("wolfowner"), collection: None };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("manager", &[]), send_back.clone());
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info("operator", &[]), send_back).unwrap();
+
+        // admins read the state with their own viewing key
+        execute(deps.as_mut(), mock_env(), mock_info("manager", &[]), ExecuteMsg::SetViewingKey { key: "pack".to_string() }).unwrap();
+        let get_state = QueryMsg::GetState { viewer: ViewerInfo { address: "manager".to_string(), viewing_key: "pack".to_string() } };
+        let state: State = from_binary(&query(deps.as_ref(), mock_env(), get_state.clone()).unwrap()).unwrap();
+        assert_eq!(None, state.shill_viewing_key);
+        assert!(state.collections.iter().all(|x| x.viewing_key.is_empty()));
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::SetViewingKey { key: "den".to_string() }).unwrap();
+        let owner_state = QueryMsg::GetState { viewer: ViewerInfo { address: "creator".to_string(), viewing_key: "den".to_string() } };
+        let state: State = from_binary(&query(deps.as_ref(), mock_env(), owner_state).unwrap()).unwrap();
+        assert!(state.shill_viewing_key.is_some());
+        assert!(state.collections.iter().all(|x| !x.viewing_key.is_empty()));
+
+        let revoke = ExecuteMsg::RevokeRole { address: Addr::unchecked("manager"), role: Role::QuestManager };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), revoke).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info("manager", &[]), ExecuteMsg::CloseQuest { quest_id: 2 });
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        assert!(query(deps.as_ref(), mock_env(), get_state).is_err());
+    }
+
+    #[test]
+    fn party_wolves_get_a_claim_time_once_the_party_forms() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let mut quest = test_quest(1);
+        quest.party_size = 2;
+        start_quest(deps.as_mut(), quest);
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), party_msg("alpha", "1", None)).unwrap();
+        let party_id: u32 = res.attributes.iter().find(|a| a.key == "party_id").unwrap().value.parse().unwrap();
+        let status = token_status(deps.as_ref(), "1");
+        assert!(status.on_quest);
+        assert_eq!(Some(party_id), status.party_id);
+        assert_eq!(None, status.claimable_at);
+
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), party_msg("beta", "2", Some(party_id))).unwrap();
+        let claimable_at = Some(mock_env().block.time.seconds() + 100);
+        assert_eq!(claimable_at, token_status(deps.as_ref(), "1").claimable_at);
+        assert_eq!(claimable_at, token_status(deps.as_ref(), "2").claimable_at);
+    }
+
+    #[test]
+    fn tracked_wolves_skip_metadata_when_sync_is_off() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), test_quest(1));
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), ExecuteMsg::SetMetadataSync { sync: false });
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::SetMetadataSync { sync: false }).unwrap();
+
+        // the first claim seeds the ledger from the metadata
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string()], collection: None, entropy: None };
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        mock_wolf_metadata(&mut deps.querier, 0, 1);
+        let res = execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim.clone()).unwrap();
+        let metadata_msgs = res.messages.iter().filter(|m| matches!(&m.msg,
+            CosmosMsg::Wasm(cosmwasm_std::WasmMsg::Execute { msg, .. }) if String::from_utf8_lossy(msg.as_slice()).contains("set_metadata"))).count();
+        assert_eq!(0, metadata_msgs);
+
+        // after that the collection isn't queried at all
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        deps.querier.update_wasm(|_| SystemResult::Ok(ContractResult::Err("metadata queried".to_string())));
+        execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim).unwrap();
+        assert_eq!(Some(WolfProgress { xp: 100, level: 2 }), wolf_progress(deps.as_ref(), "1"));
+    }
+
+    #[test]
+    fn bad_input_returns_typed_errors() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        start_quest(deps.as_mut(), test_quest(1));
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 9));
+        assert_eq!(res.unwrap_err(), ContractError::QuestNotFound { quest_id: 9 });
+
+        let garbage = ExecuteMsg::BatchReceiveNft {
+            from: Addr::unchecked("wolfowner"),
+            token_ids: vec!["1".to_string()],
+            msg: Some(Binary::from(b"not json".to_vec()))
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), garbage);
+        assert_eq!(res.unwrap_err(), ContractError::InvalidReceiveMsg {});
+
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+        let claim = ExecuteMsg::ClaimNfts { token_ids: vec!["1".to_string()], collection: None, entropy: None };
+        mock_wolf_metadata(&mut deps.querier, 0, 1);
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), claim.clone());
+        assert_eq!(res.unwrap_err(), ContractError::ClaimTooEarly {
+            token_id: "1".to_string(),
+            claimable_at: mock_env().block.time.seconds() + 100
+        });
+
+        let res = execute(deps.as_mut(), claim_env(), mock_info("someoneelse", &[]), claim.clone());
+        assert_eq!(res.unwrap_err(), ContractError::NotStaked { token_id: "1".to_string() });
+
+        deps.querier.update_wasm(|_| SystemResult::Ok(ContractResult::Ok(Binary::from(
+            br#"{"nft_dossier":{"owner":null,"public_metadata":{"token_uri":null,"extension":{"attributes":[{"trait_type":"XP","value":"lots"}]}},"private_metadata":null,"display_private_metadata_error":null,"owner_is_public":false,"public_ownership_expiration":null,"private_metadata_is_public":false,"private_metadata_is_public_expiration":null,"token_approvals":null,"inventory_approvals":null}}"#.to_vec()
+        ))));
+        let res = execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), claim);
+        assert_eq!(res.unwrap_err(), ContractError::InvalidXpValue { token_id: "1".to_string(), value: "lots".to_string() });
+
+        // durations that overflow the block time are an error instead of a panic
+        let mut endless = test_quest(2);
+        endless.duration_until_join_closed = u64::MAX;
+        start_quest(deps.as_mut(), endless);
+        let res = execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 2));
+        assert!(matches!(res.unwrap_err(), ContractError::Std(StdError::Overflow { .. })));
+    }
+
+    #[test]
+    fn abandon_quest_returns_wolves_and_charges_fee() {
+        let mut deps = mock_dependencies();
+        init_with_quest(deps.as_mut());
+        let mut quest = test_quest(1);
+        quest.abandon_policy = AbandonPolicy { xp_percent: 0, shill_fee: Uint128::from(5u32) };
+        start_quest(deps.as_mut(), quest);
+        execute(deps.as_mut(), mock_env(), mock_info(NFT_CONTRACT, &[]), receive_msg("wolfowner", 1)).unwrap();
+
+        let abandon = ExecuteMsg::AbandonQuest { token_ids: vec!["1".to_string()], collection: None };
+        let res = execute(deps.as_mut(), claim_env(), mock_info("wolfowner", &[]), abandon.clone());
+        assert_eq!(res.unwrap_err(), ContractError::QuestCompleted { token_id: "1".to_string() });
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), abandon.clone());
+        assert_eq!(res.unwrap_err(), ContractError::AbandonFeeNotPaid {
+            quest_id: 1,
+            required: Uint128::from(5u32),
+            paid: Uint128::from(0u32)
+        });
+
+        // the fee comes out of the SHILL paid into the quest's escrow
+        execute(deps.as_mut(), mock_env(), mock_info("shilltoken", &[]), pay_fee(8)).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info("wolfowner", &[]), abandon).unwrap();
+        // only the nft transfer back
+        assert_eq!(1, res.messages.len());
+        assert!(res.attributes.iter().any(|a| a.key == "shill_fee" && a.value == "5"));
+
+        let owner_raw = deps.api.addr_canonicalize("wolfowner").unwrap();
+        assert_eq!(Uint128::from(3u32), entry_fee_store(1).get(&deps.storage, &owner_raw).unwrap().paid);
+        assert!(staked_nfts_store().get(&deps.storage, &owner_raw).unwrap().is_empty());
+        let history = user_staked_nft_history(deps.as_ref(), &owner_raw, 0, 10).unwrap();
+        assert!(history[0].abandoned);
+        assert_eq!(Uint128::from(0u32), history[0].reward_amount);
+        assert_eq!(0, history[0].xp_reward);
+    }
+}
@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -11,6 +11,141 @@ pub enum ContractError {
     // issued when message sender != owner
     Unauthorized {},
 
+    #[error("Unauthorized NFT contract: {sender}")]
+    // issued when BatchReceiveNft is not sent by a whitelisted SNIP-721 contract
+    UnauthorizedNftContract { sender: String },
+
+    #[error("Invalid message received")]
+    // issued when the msg of a receive callback is missing or can't be decoded
+    InvalidReceiveMsg {},
+
+    #[error("Quest {quest_id} doesn't exist")]
+    QuestNotFound { quest_id: i32 },
+
+    #[error("Quest {quest_id} already exists")]
+    QuestIdExists { quest_id: i32 },
+
+    #[error("Quest {quest_id} is not open for new wolves")]
+    // issued when joining outside of the quest's join window
+    QuestNotOpen { quest_id: i32 },
+
+    #[error("Quest {quest_id} is closed")]
+    QuestClosed { quest_id: i32 },
+
+    #[error("Quest {quest_id} can't be changed after wolves have joined")]
+    QuestHasParticipants { quest_id: i32 },
+
+    #[error("Wrong number of wolves for this quest: expected {expected}, received {received}")]
+    WrongNftCount { expected: i32, received: i32 },
+
+    #[error("Token {token_id} is not staked by this address")]
+    NotStaked { token_id: String },
+
+    #[error("Token {token_id} finished its quest and has to be claimed")]
+    // issued when abandoning a wolf that can already be claimed
+    QuestCompleted { token_id: String },
+
+    #[error("Token {token_id} can't be claimed before {claimable_at}")]
+    ClaimTooEarly { token_id: String, claimable_at: u64 },
+
+    #[error("Unable to get metadata of token {token_id} from the nft contract")]
+    MissingMetadata { token_id: String },
+
+    #[error("Token {token_id} has an invalid XP value: {value}")]
+    InvalidXpValue { token_id: String, value: String },
+
+    #[error("Token {token_id} has an invalid level value: {value}")]
+    InvalidLevelValue { token_id: String, value: String },
+
+    #[error("Collection {collection} is not registered")]
+    UnknownCollection { collection: String },
+
+    #[error("Collection {collection} is already registered")]
+    CollectionExists { collection: String },
+
+    #[error("Quest {quest_id} doesn't accept wolves from {collection}")]
+    CollectionNotAccepted { quest_id: i32, collection: String },
+
+    #[error("Party {party_id} doesn't exist")]
+    PartyNotFound { party_id: u32 },
+
+    #[error("Party {party_id} is full")]
+    PartyFull { party_id: u32 },
+
+    #[error("Already a member of party {party_id}")]
+    AlreadyInParty { party_id: u32 },
+
+    #[error("Quest {quest_id} is not a party quest")]
+    NotAPartyQuest { quest_id: i32 },
+
+    #[error("Token {token_id} is waiting for its party to fill up")]
+    // issued when claiming a wolf whose party never started the hunt
+    PartyNotFormed { token_id: String },
+
+    #[error("Invalid outcome rules for quest {quest_id}")]
+    // issued when chances exceed 100, the XP range is inverted, critical
+    // successes pay less than a success or the best roll overflows the XP
+    InvalidOutcomeRules { quest_id: i32 },
+
+    #[error("No loot to claim")]
+    NoPendingLoot {},
+
+    #[error("Token {token} is not registered for entry fees")]
+    UnknownFeeToken { token: String },
+
+    #[error("Quest {quest_id} has no entry fee")]
+    NoEntryFee { quest_id: i32 },
+
+    #[error("Quest {quest_id} doesn't take entry fees in {token}")]
+    WrongFeeToken { quest_id: i32, token: String },
+
+    #[error("Invalid entry fee for quest {quest_id}")]
+    // issued when fees not paid in SHILL are routed to the reward pool or share
+    // their escrow with an abandon fee
+    InvalidEntryFee { quest_id: i32 },
+
+    #[error("Entry fee for quest {quest_id} not paid: required {required}, paid {paid}")]
+    EntryFeeNotPaid { quest_id: i32, required: Uint128, paid: Uint128 },
+
+    #[error("Abandon fee for quest {quest_id} not paid: required {required}, paid {paid}")]
+    AbandonFeeNotPaid { quest_id: i32, required: Uint128, paid: Uint128 },
+    #[error("Rewards are paid in SHILL, not in {token}")]
+    WrongRewardToken { token: String },
+
+    #[error("Reward pool of quest {quest_id} can't cover the wolves: required {required}, available {available}")]
+    InsufficientRewardPool { quest_id: i32, required: Uint128, available: Uint128 },
+
+    #[error("Only {available} SHILL is not owed to players")]
+    // issued when withdrawing SHILL reserved for rewards or held as entry fees
+    InsufficientFreeBalance { available: Uint128 },
+
+    #[error("Token {token_id} is level {level}, quest needs at least level {min_level}")]
+    LevelTooLow { token_id: String, level: i32, min_level: i32 },
+
+    #[error("Token {token_id} is level {level}, quest allows at most level {max_level}")]
+    LevelTooHigh { token_id: String, level: i32, max_level: i32 },
+
+    #[error("Token {token_id} doesn't meet the quest's {trait_type} requirement")]
+    RequirementNotMet { token_id: String, trait_type: String },
+
+    #[error("At least one level has to be configured")]
+    NoLevels {},
+
+    #[error("Level {level} doesn't follow the previous level")]
+    NonContiguousLevel { level: i32 },
+
+    #[error("Level {level} needs more XP than the previous level")]
+    XpNotIncreasing { level: i32 },
+
+    #[error("Level cap {level_cap} is not in the level table")]
+    InvalidLevelCap { level_cap: i32 },
+
+    #[error("No ownership transfer was proposed")]
+    NoPendingOwner {},
+
+    #[error("XP and level trait names must be set and differ")]
+    InvalidProgressTraits {},
+
     #[error("Custom Error val: {val:?}")]
     CustomError { val: String },
     // Add any other custom errors you like here.
@@ -0,0 +1,109 @@
+use crate::msg::Level;
+
+/// Returns the highest level of the table the XP reaches, None when it is
+/// below every level
+pub fn level_for_xp(levels: &[Level], xp: i32) -> Option<i32> {
+    levels.iter()
+        .filter(|x| x.xp_needed <= xp)
+        .map(|x| x.level)
+        .max()
+}
+
+/// Returns the level of a wolf after reaching the given XP. Wolves level up as
+/// far as their XP takes them but never past the level cap, and never lose levels
+pub fn next_level(levels: &[Level], xp: i32, current_level: i32, level_cap: i32) -> i32 {
+    match level_for_xp(levels, xp) {
+        Some(level) if level > current_level => std::cmp::max(current_level, std::cmp::min(level, level_cap)),
+        _ => current_level
+    }
+}
+
+/// Returns every level gained going from one level to another
+pub fn levels_gained(old_level: i32, new_level: i32) -> Vec<i32> {
+    ((old_level + 1)..=new_level).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels() -> Vec<Level> {
+        vec![
+            Level { level: 1, xp_needed: 0 },
+            Level { level: 2, xp_needed: 100 },
+            Level { level: 3, xp_needed: 250 },
+            Level { level: 4, xp_needed: 500 }
+        ]
+    }
+
+    #[test]
+    fn level_for_xp_thresholds() {
+        assert_eq!(Some(1), level_for_xp(&levels(), 0));
+        assert_eq!(Some(1), level_for_xp(&levels(), 99));
+        assert_eq!(Some(2), level_for_xp(&levels(), 100));
+        assert_eq!(Some(2), level_for_xp(&levels(), 249));
+        assert_eq!(Some(3), level_for_xp(&levels(), 250));
+        assert_eq!(Some(4), level_for_xp(&levels(), 500));
+    }
+
+    #[test]
+    fn level_for_xp_beyond_the_table() {
+        assert_eq!(Some(4), level_for_xp(&levels(), 1_000_000));
+        assert_eq!(Some(4), level_for_xp(&levels(), i32::MAX));
+    }
+
+    #[test]
+    fn level_for_xp_below_the_table() {
+        let levels = vec![Level { level: 1, xp_needed: 10 }, Level { level: 2, xp_needed: 20 }];
+        assert_eq!(None, level_for_xp(&levels, 9));
+        assert_eq!(None, level_for_xp(&levels, -5));
+        assert_eq!(None, level_for_xp(&[], 100));
+    }
+
+    #[test]
+    fn level_for_xp_ignores_table_order() {
+        let mut levels = levels();
+        levels.reverse();
+        assert_eq!(Some(3), level_for_xp(&levels, 300));
+    }
+
+    #[test]
+    fn single_level_up() {
+        assert_eq!(2, next_level(&levels(), 100, 1, 4));
+        assert_eq!(1, next_level(&levels(), 99, 1, 4));
+    }
+
+    #[test]
+    fn multi_level_jump() {
+        assert_eq!(4, next_level(&levels(), 600, 1, 4));
+        assert_eq!(3, next_level(&levels(), 300, 1, 4));
+    }
+
+    #[test]
+    fn cap_stops_level_ups() {
+        assert_eq!(2, next_level(&levels(), 600, 1, 2));
+        assert_eq!(3, next_level(&levels(), 1_000_000, 2, 3));
+    }
+
+    #[test]
+    fn wolves_at_or_above_the_cap_keep_their_level() {
+        assert_eq!(2, next_level(&levels(), 600, 2, 2));
+        // a wolf above the cap from before the cap was lowered isn't demoted
+        assert_eq!(4, next_level(&levels(), 600, 4, 2));
+    }
+
+    #[test]
+    fn levels_are_never_lost() {
+        assert_eq!(3, next_level(&levels(), 0, 3, 4));
+        assert_eq!(3, next_level(&[], 1000, 3, 4));
+        assert_eq!(1, next_level(&levels(), -10, 1, 4));
+    }
+
+    #[test]
+    fn levels_gained_lists_every_level() {
+        assert_eq!(vec![2, 3, 4], levels_gained(1, 4));
+        assert_eq!(vec![2], levels_gained(1, 2));
+        assert!(levels_gained(2, 2).is_empty());
+        assert!(levels_gained(3, 2).is_empty());
+    }
+}
@@ -1,5 +1,11 @@
 pub mod contract;
+mod bonus;
 mod error;
+mod leveling;
+mod loot;
+mod migrate;
+mod outcome;
+mod rewards;
 pub mod msg;
 pub mod state;
 mod rand;
@@ -0,0 +1,151 @@
+use cosmwasm_std::{Addr, StdResult, Uint128};
+use crate::msg::{ LootTable, LootDrop };
+use crate::rand::Prng;
+
+/// Rolls every loot table of a quest for one wolf and returns what dropped.
+/// Items handed out by Snip721 entries are removed from their table, so the
+/// quest has to be saved when one drops
+pub fn roll_loot(rng: &mut Prng, tables: &mut [LootTable]) -> Vec<LootDrop> {
+    let mut drops: Vec<LootDrop> = Vec::new();
+    for table in tables.iter_mut() {
+        let chance_roll = (rng.next_u32() % 100) as u8;
+        if chance_roll >= table.drop_chance {
+            continue;
+        }
+
+        // entries that ran out of items can't drop anymore
+        let total: u64 = table.entries.iter()
+            .filter(|x| has_items(&x.drop))
+            .map(|x| x.weight as u64)
+            .sum();
+        if total == 0 {
+            continue;
+        }
+
+        let mut pick = rng.next_u32() as u64 % total;
+        for entry in table.entries.iter_mut().filter(|x| has_items(&x.drop)) {
+            if pick >= entry.weight as u64 {
+                pick -= entry.weight as u64;
+                continue;
+            }
+            drops.push(match &mut entry.drop {
+                LootDrop::Snip721 { contract, token_ids } => LootDrop::Snip721 {
+                    contract: contract.clone(),
+                    token_ids: vec![token_ids.remove(0)]
+                },
+                drop => drop.clone()
+            });
+            break;
+        }
+    }
+    drops
+}
+
+/// Returns the most of a SNIP-20 token one wolf can find, every table drops at
+/// most one of its entries
+pub fn max_token_loot(tables: &[LootTable], token: &Addr) -> StdResult<Uint128> {
+    let mut total = Uint128::zero();
+    for table in tables.iter().filter(|table| table.drop_chance > 0) {
+        let most = table.entries.iter()
+            .filter(|x| x.weight > 0)
+            .filter_map(|x| match &x.drop {
+                LootDrop::Snip20 { contract, amount } if &contract.address == token => Some(*amount),
+                _ => None
+            })
+            .max()
+            .unwrap_or_default();
+        total = total.checked_add(most)?;
+    }
+    Ok(total)
+}
+
+/// Returns true when the loot tables can hand out metadata traits
+pub fn has_trait_drops(tables: &[LootTable]) -> bool {
+    tables.iter().any(|table| table.entries.iter().any(|x| matches!(x.drop, LootDrop::Trait { .. })))
+}
+
+fn has_items(drop: &LootDrop) -> bool {
+    match drop {
+        LootDrop::Snip721 { token_ids, .. } => !token_ids.is_empty(),
+        _ => true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::{ LootEntry, ContractInfo };
+
+    fn contract(address: &str) -> ContractInfo {
+        ContractInfo { address: Addr::unchecked(address), code_hash: "hash".to_string() }
+    }
+
+    fn gems(amount: u32) -> LootDrop {
+        LootDrop::Snip20 { contract: contract("gems"), amount: Uint128::from(amount) }
+    }
+
+    #[test]
+    fn drop_chance_gates_the_table() {
+        let mut rng = Prng::new(b"seed", b"claim");
+        let mut never = vec![LootTable { drop_chance: 0, entries: vec![LootEntry { weight: 1, drop: gems(1) }] }];
+        let mut always = vec![LootTable { drop_chance: 100, entries: vec![LootEntry { weight: 1, drop: gems(1) }] }];
+        for _ in 0..20 {
+            assert!(roll_loot(&mut rng, &mut never).is_empty());
+            assert_eq!(vec![gems(1)], roll_loot(&mut rng, &mut always));
+        }
+    }
+
+    #[test]
+    fn weights_pick_the_entry() {
+        let mut rng = Prng::new(b"seed", b"claim");
+        let mut tables = vec![LootTable {
+            drop_chance: 100,
+            entries: vec![
+                LootEntry { weight: 0, drop: gems(1) },
+                LootEntry { weight: 3, drop: gems(2) },
+                LootEntry { weight: 1, drop: gems(3) }
+            ]
+        }];
+        let mut picked = [0; 4];
+        for _ in 0..200 {
+            match roll_loot(&mut rng, &mut tables).as_slice() {
+                [LootDrop::Snip20 { amount, .. }] => picked[amount.u128() as usize] += 1,
+                other => panic!("unexpected drops {:?}", other)
+            }
+        }
+        assert_eq!(0, picked[1]);
+        assert!(picked[2] > picked[3]);
+        assert!(picked[3] > 0);
+    }
+
+    #[test]
+    fn items_run_out() {
+        let mut rng = Prng::new(b"seed", b"claim");
+        let mut tables = vec![LootTable {
+            drop_chance: 100,
+            entries: vec![LootEntry {
+                weight: 1,
+                drop: LootDrop::Snip721 { contract: contract("gear"), token_ids: vec!["a".to_string(), "b".to_string()] }
+            }]
+        }];
+        let item = |id: &str| LootDrop::Snip721 { contract: contract("gear"), token_ids: vec![id.to_string()] };
+        assert_eq!(vec![item("a")], roll_loot(&mut rng, &mut tables));
+        assert_eq!(vec![item("b")], roll_loot(&mut rng, &mut tables));
+        assert!(roll_loot(&mut rng, &mut tables).is_empty());
+    }
+
+    #[test]
+    fn max_token_loot_takes_the_best_entry_of_every_table() {
+        let table = |drop_chance: u8| LootTable {
+            drop_chance,
+            entries: vec![
+                LootEntry { weight: 1, drop: gems(2) },
+                LootEntry { weight: 1, drop: gems(5) },
+                LootEntry { weight: 0, drop: gems(50) }
+            ]
+        };
+        let gems_addr = Addr::unchecked("gems");
+        assert_eq!(Uint128::from(10u32), max_token_loot(&[table(10), table(100), table(0)], &gems_addr).unwrap());
+        assert_eq!(Uint128::zero(), max_token_loot(&[table(100)], &Addr::unchecked("shilltoken")).unwrap());
+    }
+}
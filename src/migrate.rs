@@ -0,0 +1,366 @@
+use schemars::JsonSchema;
+use serde::{ Deserialize, Serialize};
+
+use cosmwasm_std::{Addr, Api, CanonicalAddr, StdError, StdResult, Storage, Uint128};
+use secret_toolkit::{
+    storage:: { Item, Keymap, AppendStore },
+    snip721:: { Trait }
+};
+use crate::msg::{Quest, Token, HistoryToken, ContractInfo, Collection, AbandonPolicy, BonusRule, MatchMode, TraitCondition, TraitComparison};
+use crate::state::{State, Funds, FUNDS_ITEM, CONFIG_KEY, CONFIG_ITEM, quests_store, STAKED_NFTS_KEY, staked_nfts_store,
+    STAKED_NFTS_HISTORY_KEY, PRNG_SEED_ITEM, staked_history_store, token_index_store, quest_participants_store};
+use crate::rand::sha_256;
+use crate::rewards::max_wolf_reward;
+
+// Layouts as they were stored by the first release of the contract. They are
+// only used to read old data during a migration and must never be changed.
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct QuestV1 {
+    pub quest_id: i32,
+    pub title: String,
+    pub description: String,
+    pub duration_until_join_closed: u64,
+    pub duration_in_staking: u64,
+    pub num_of_nfts: i32,
+    pub start_time: u64,
+    pub create_date: u64,
+    pub xp_reward: i32,
+    pub shill_reward: Uint128,
+    pub shill_trait_bonus_reward: Uint128,
+    pub bonus_reward_traits: Vec<Trait>,
+    pub wolves_on_the_hunt: i32
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct TokenV1 {
+    pub token_id: String,
+    pub owner: Addr,
+    pub sender: Addr,
+    pub quest_id: i32,
+    pub staked_date: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct HistoryTokenV1 {
+    pub token_id: String,
+    pub owner: Addr,
+    pub sender: Addr,
+    pub quest_id: i32,
+    pub staked_date: Option<u64>,
+    pub claimed_date: Option<u64>,
+    pub reward_amount: Uint128,
+    pub xp_reward: i32
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct StateV1 {
+    pub quests: Vec<QuestV1>,
+    pub locked_nfts: Vec<TokenV1>,
+    pub owner: Addr,
+    pub viewing_key: Option<String>,
+    pub quest_contract: ContractInfo,
+    pub level_cap: i32,
+    pub shill_viewing_key: Option<String>,
+    pub shill_contract: ContractInfo
+}
+
+pub static CONFIG_ITEM_V1: Item<StateV1> = Item::new(CONFIG_KEY);
+
+pub fn staked_nfts_store_v1() -> Keymap<'static, CanonicalAddr, Vec<TokenV1>> {
+    Keymap::new(STAKED_NFTS_KEY)
+}
+/// version 1 suffixed the history with the human address of the claimer
+pub static STAKED_NFTS_HISTORY_STORE_V1: AppendStore<HistoryTokenV1> = AppendStore::new(STAKED_NFTS_HISTORY_KEY);
+
+impl From<QuestV1> for Quest {
+    fn from(quest: QuestV1) -> Self {
+        Quest {
+            quest_id: quest.quest_id,
+            title: quest.title,
+            description: quest.description,
+            duration_until_join_closed: quest.duration_until_join_closed,
+            duration_in_staking: quest.duration_in_staking,
+            num_of_nfts: quest.num_of_nfts,
+            party_size: 0,
+            start_time: quest.start_time,
+            create_date: quest.create_date,
+            xp_reward: quest.xp_reward,
+            shill_reward: quest.shill_reward,
+            collections: vec![],
+            min_level: None,
+            max_level: None,
+            required_traits: vec![],
+            bonus_rules: bonus_rules_from_traits(quest.shill_trait_bonus_reward, quest.bonus_reward_traits),
+            // version 1 paid the flat bonus at most once per wolf
+            max_bonus_reward: Some(quest.shill_trait_bonus_reward),
+            abandon_policy: AbandonPolicy::default(),
+            outcome_rules: None,
+            loot_tables: vec![],
+            entry_fee: None,
+            reward_pool: Uint128::from(0u32),
+            reserved: Uint128::from(0u32),
+            wolves_on_the_hunt: quest.wolves_on_the_hunt,
+            wolves_completed: 0,
+            closed: false
+        }
+    }
+}
+
+/// Version 1 paid a flat bonus when any of the bonus traits matched exactly
+fn bonus_rules_from_traits(bonus: Uint128, traits: Vec<Trait>) -> Vec<BonusRule> {
+    let conditions: Vec<TraitCondition> = traits.into_iter()
+        .filter_map(|t| t.trait_type.map(|trait_type| TraitCondition {
+            trait_type,
+            comparison: TraitComparison::Eq,
+            value: t.value
+        }))
+        .collect();
+
+    if conditions.is_empty() || bonus.is_zero() {
+        return vec![];
+    }
+    vec![BonusRule { conditions, match_mode: MatchMode::Any, shill_bonus: bonus }]
+}
+
+impl From<TokenV1> for Token {
+    fn from(nft: TokenV1) -> Self {
+        Token {
+            token_id: nft.token_id,
+            owner: nft.owner,
+            // version 1 stored the NFT contract that sent the token as sender
+            collection: nft.sender.clone(),
+            sender: nft.sender,
+            quest_id: nft.quest_id,
+            staked_date: nft.staked_date,
+            party_id: None
+        }
+    }
+}
+
+impl From<HistoryTokenV1> for HistoryToken {
+    fn from(history: HistoryTokenV1) -> Self {
+        HistoryToken {
+            token_id: history.token_id,
+            owner: history.owner,
+            collection: history.sender.clone(),
+            sender: history.sender,
+            quest_id: history.quest_id,
+            party_id: None,
+            staked_date: history.staked_date,
+            claimed_date: history.claimed_date,
+            // version 1 stored the running total of the claim batch, there is
+            // no way to split it so it is kept as the base reward
+            reward_amount: history.reward_amount,
+            xp_reward: history.xp_reward,
+            base_reward: history.reward_amount,
+            bonus_reward: Uint128::from(0u32),
+            level: None,
+            abandoned: false,
+            roll: None,
+            loot: vec![]
+        }
+    }
+}
+
+/// Upgrades storage written by version 1 of the contract to version 2
+pub fn upgrade_v1_to_v2(storage: &mut dyn Storage, api: &dyn Api, entropy: Option<String>) -> StdResult<()> {
+    migrate_config(storage, entropy)?;
+    migrate_staked_nfts(storage)?;
+    migrate_staked_history(storage, api)?;
+    Ok(())
+}
+
+/// Moves every quest out of the config singleton into `quests_store`, rewrites
+/// the config in the current layout and creates the random seed version 1 didn't store
+fn migrate_config(storage: &mut dyn Storage, entropy: Option<String>) -> StdResult<()> {
+    let old_state = CONFIG_ITEM_V1.load(storage)?;
+
+    for quest in old_state.quests {
+        let quest_id = quest.quest_id;
+        quests_store().insert(storage, &quest_id, &Quest::from(quest))?;
+    }
+
+    // the seed can't come from anything the owner or the chain already knows
+    let entropy = entropy.ok_or_else(|| StdError::generic_err("Entropy is required to upgrade from version 1"))?;
+    PRNG_SEED_ITEM.save(storage, &sha_256(entropy.as_bytes()).to_vec())?;
+
+    let viewing_key = old_state.viewing_key.unwrap_or_default();
+
+    let state = State {
+        locked_nfts: old_state.locked_nfts.into_iter().map(Token::from).collect(),
+        owner: old_state.owner,
+        collections: vec![Collection {
+            contract: old_state.quest_contract,
+            viewing_key
+        }],
+        level_cap: old_state.level_cap,
+        shill_viewing_key: old_state.shill_viewing_key,
+        fee_tokens: vec![old_state.shill_contract.clone()],
+        shill_contract: old_state.shill_contract,
+        xp_trait: "XP".to_string(),
+        level_trait: "LVL".to_string(),
+        private_progress: false,
+        sync_metadata: true,
+        pending_owner: None,
+        roles: vec![]
+    };
+    CONFIG_ITEM.save(storage, &state)
+}
+
+/// Rewrites every user's staked wolves in the current `Token` layout so that
+/// nothing has to be unstaked during an upgrade, and indexes them by token id
+/// and quest. Version 1 never counted wolves leaving a quest, so the number of
+/// wolves on the hunt is recounted, and it paid rewards from the contract's
+/// balance, so the rewards of the wolves on the hunt are reserved from it
+fn migrate_staked_nfts(storage: &mut dyn Storage) -> StdResult<()> {
+    let staked: Vec<(CanonicalAddr, Vec<TokenV1>)> = staked_nfts_store_v1().iter(storage)?.collect::<StdResult<Vec<_>>>()?;
+
+    let mut on_the_hunt: Vec<(i32, i32)> = Vec::new();
+    for (owner_raw, nfts) in staked {
+        let nfts: Vec<Token> = nfts.into_iter().map(Token::from).collect();
+        for nft in nfts.iter() {
+            token_index_store(&nft.collection).insert(storage, &nft.token_id, nft)?;
+            quest_participants_store(nft.quest_id).insert(storage, &(nft.collection.clone(), nft.token_id.clone()), &nft.owner)?;
+            match on_the_hunt.iter_mut().find(|(quest_id, _)| *quest_id == nft.quest_id) {
+                Some((_, count)) => *count += 1,
+                None => on_the_hunt.push((nft.quest_id, 1))
+            }
+        }
+        staked_nfts_store().insert(storage, &owner_raw, &nfts)?;
+    }
+
+    let state = CONFIG_ITEM.load(storage)?;
+    let mut funds = Funds::default();
+    let quests: Vec<(i32, Quest)> = quests_store().iter(storage)?.collect::<StdResult<Vec<_>>>()?;
+    for (quest_id, mut quest) in quests {
+        quest.wolves_on_the_hunt = on_the_hunt.iter().find(|(id, _)| *id == quest_id).map_or(0, |(_, count)| *count);
+        quest.reserved = max_wolf_reward(&quest, None, &state.shill_contract.address)?
+            .checked_mul(Uint128::from(quest.wolves_on_the_hunt as u32))?;
+        funds.reserved = funds.reserved.checked_add(quest.reserved)?;
+        quests_store().insert(storage, &quest_id, &quest)?;
+    }
+    FUNDS_ITEM.save(storage, &funds)
+}
+
+/// Moves history written under the human address of the claimer to the
+/// canonical address suffix that the history queries read from. Everyone who
+/// ever claimed still has an entry in `staked_nfts_store`, so its keys cover
+/// every history that has to be moved.
+fn migrate_staked_history(storage: &mut dyn Storage, api: &dyn Api) -> StdResult<()> {
+    let owners: Vec<CanonicalAddr> = staked_nfts_store().iter_keys(storage)?.collect::<StdResult<Vec<_>>>()?;
+
+    for owner_raw in owners {
+        let owner = api.addr_humanize(&owner_raw)?;
+        let old_store = STAKED_NFTS_HISTORY_STORE_V1.add_suffix(owner.as_str().as_bytes());
+        let old_history: Vec<HistoryTokenV1> = old_store.iter(storage)?.collect::<StdResult<Vec<_>>>()?;
+        if old_history.is_empty() {
+            continue;
+        }
+
+        let new_store = staked_history_store(&owner_raw);
+        for history in old_history {
+            new_store.push(storage, &HistoryToken::from(history))?;
+        }
+        while old_store.get_len(storage)? > 0 {
+            old_store.pop(storage)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use crate::contract::migrate;
+    use crate::msg::MigrateMsg;
+    use crate::state::{CONTRACT_VERSION, CONTRACT_VERSION_ITEM};
+
+    fn v1_state() -> StateV1 {
+        StateV1 {
+            quests: vec![QuestV1 {
+                quest_id: 7,
+                title: "Hunt".to_string(),
+                description: "Into the woods".to_string(),
+                duration_until_join_closed: 1000,
+                duration_in_staking: 100,
+                num_of_nfts: 1,
+                start_time: 1,
+                create_date: 1,
+                xp_reward: 50,
+                shill_reward: Uint128::from(10u32),
+                shill_trait_bonus_reward: Uint128::from(0u32),
+                bonus_reward_traits: vec![],
+                wolves_on_the_hunt: 1
+            }],
+            locked_nfts: vec![],
+            owner: Addr::unchecked("creator"),
+            viewing_key: Some("key".to_string()),
+            quest_contract: ContractInfo { address: Addr::unchecked("wolfpacknft"), code_hash: "nft_hash".to_string() },
+            level_cap: 2,
+            shill_viewing_key: Some("shill".to_string()),
+            shill_contract: ContractInfo { address: Addr::unchecked("shilltoken"), code_hash: "shill_hash".to_string() }
+        }
+    }
+
+    #[test]
+    fn migrate_v1_layout() {
+        let mut deps = mock_dependencies();
+        CONFIG_ITEM_V1.save(&mut deps.storage, &v1_state()).unwrap();
+        let owner_raw = deps.api.addr_canonicalize("wolfowner").unwrap();
+        let staked = vec![TokenV1 {
+            token_id: "1".to_string(),
+            owner: Addr::unchecked("wolfowner"),
+            sender: Addr::unchecked("wolfpacknft"),
+            quest_id: 7,
+            staked_date: Some(5)
+        }];
+        staked_nfts_store_v1().insert(&mut deps.storage, &owner_raw, &staked).unwrap();
+        let old_history = STAKED_NFTS_HISTORY_STORE_V1.add_suffix(b"wolfowner");
+        old_history.push(&mut deps.storage, &HistoryTokenV1 {
+            token_id: "2".to_string(),
+            owner: Addr::unchecked("wolfowner"),
+            sender: Addr::unchecked("wolfpacknft"),
+            quest_id: 7,
+            staked_date: Some(1),
+            claimed_date: Some(200),
+            reward_amount: Uint128::from(10u32),
+            xp_reward: 50
+        }).unwrap();
+
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg { entropy: None });
+        assert_eq!(res.unwrap_err(), StdError::generic_err("Entropy is required to upgrade from version 1"));
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg { entropy: Some("howl".to_string()) }).unwrap();
+        // receivers are registered again for the collection and for SHILL
+        assert_eq!(2, res.messages.len());
+
+        assert_eq!(CONTRACT_VERSION, CONTRACT_VERSION_ITEM.load(&deps.storage).unwrap());
+        let state = CONFIG_ITEM.load(&deps.storage).unwrap();
+        assert_eq!(Addr::unchecked("creator"), state.owner);
+        assert_eq!(Addr::unchecked("wolfpacknft"), state.collections[0].contract.address);
+        assert_eq!("key", state.collections[0].viewing_key);
+        let quest = quests_store().get(&deps.storage, &7).unwrap();
+        assert_eq!(1, quest.wolves_on_the_hunt);
+        // the wolf still on the hunt keeps its reward reserved
+        assert_eq!(Uint128::from(10u32), quest.reserved);
+        assert_eq!(Uint128::from(10u32), FUNDS_ITEM.load(&deps.storage).unwrap().reserved);
+        assert!(!quest.closed);
+        let nfts = staked_nfts_store().get(&deps.storage, &owner_raw).unwrap();
+        assert_eq!("1", nfts[0].token_id);
+        assert_eq!(Some(5), nfts[0].staked_date);
+        assert_eq!(Addr::unchecked("wolfpacknft"), nfts[0].collection);
+        let indexed = token_index_store(&Addr::unchecked("wolfpacknft")).get(&deps.storage, &"1".to_string()).unwrap();
+        assert_eq!(Addr::unchecked("wolfowner"), indexed.owner);
+        let history = staked_history_store(&owner_raw).paging(&deps.storage, 0, 10).unwrap();
+        assert_eq!(1, history.len());
+        assert_eq!("2", history[0].token_id);
+        // a fresh handle, the one above still caches the pre-migration length
+        let old_history = STAKED_NFTS_HISTORY_STORE_V1.add_suffix(b"wolfowner");
+        assert_eq!(0, old_history.get_len(&deps.storage).unwrap());
+
+        // running the migration again is a no-op
+        migrate(deps.as_mut(), mock_env(), MigrateMsg { entropy: None }).unwrap();
+        assert_eq!(1, quests_store().get_len(&deps.storage).unwrap());
+    }
+}
@@ -4,7 +4,7 @@ use cosmwasm_std::{
    Addr, Binary, Uint128
 };
 use secret_toolkit::{ 
-    snip721:: { ViewerInfo, Trait },
+    snip721:: { ViewerInfo, Metadata },
     permit:: { Permit }
 };
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -17,12 +17,26 @@ pub struct InstantiateMsg {
       pub shill_contract: ContractInfo
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct MigrateMsg {
+    /// entropy for the contract's random seed, required when upgrading a
+    /// contract that was instantiated without one
+    pub entropy: Option<String>
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct Level {
     pub level: i32,
     pub xp_needed: i32
 }
 
+/// XP and level of a wolf as tracked by the contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct WolfProgress {
+    pub xp: i32,
+    pub level: i32
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct Quest {
     pub quest_id: i32,
@@ -31,18 +45,244 @@ pub struct Quest {
     pub duration_until_join_closed: u64,
     pub duration_in_staking: u64,
     pub num_of_nfts: i32,
+    /// players that hunt together, each bringing num_of_nfts wolves. Rewards of
+    /// party quests are split evenly among the members, 0 or 1 for solo quests
+    #[serde(default)]
+    pub party_size: i32,
+    /// collections wolves can join from, empty means any registered collection
+    #[serde(default)]
+    pub collections: Vec<Addr>,
+    /// lowest LVL a wolf needs to join
+    #[serde(default)]
+    pub min_level: Option<i32>,
+    /// highest LVL a wolf can have to join
+    #[serde(default)]
+    pub max_level: Option<i32>,
+    /// every condition has to match the traits of a wolf joining the quest
+    #[serde(default)]
+    pub required_traits: Vec<TraitCondition>,
     pub start_time: u64,
     pub create_date: u64,
     pub xp_reward: i32,
     pub shill_reward: Uint128,
-    pub shill_trait_bonus_reward: Uint128,
-    pub bonus_reward_traits: Vec<Trait>,
-    pub wolves_on_the_hunt: i32
+    /// every matching rule adds its bonus to the wolf's reward
+    #[serde(default)]
+    pub bonus_rules: Vec<BonusRule>,
+    /// caps the stacked bonus a single wolf can earn
+    pub max_bonus_reward: Option<Uint128>,
+    /// what happens to wolves that leave the quest early, no rewards and no fee by default
+    #[serde(default)]
+    pub abandon_policy: AbandonPolicy,
+    /// rolls the outcome of every claimed wolf, None for quests that always succeed
+    #[serde(default)]
+    pub outcome_rules: Option<OutcomeRules>,
+    /// every table is rolled for each wolf that finishes the hunt
+    #[serde(default)]
+    pub loot_tables: Vec<LootTable>,
+    /// fee every wolf pays to join, paid beforehand by sending the token to this contract
+    #[serde(default)]
+    pub entry_fee: Option<EntryFee>,
+    /// SHILL deposited for this quest's rewards that isn't reserved yet
+    #[serde(default)]
+    pub reward_pool: Uint128,
+    /// SHILL reserved for the most the wolves on the hunt can earn
+    #[serde(default)]
+    pub reserved: Uint128,
+    /// wolves currently on the quest
+    pub wolves_on_the_hunt: i32,
+    /// wolves that were claimed after finishing the quest
+    #[serde(default)]
+    pub wolves_completed: i32,
+    /// set by the admin with CloseQuest to stop new wolves from joining
+    #[serde(default)]
+    pub closed: bool
+}
+
+/// A solo quest paying 10 SHILL and 50 XP, shared by the test modules
+#[cfg(test)]
+impl Default for Quest {
+    fn default() -> Self {
+        Quest {
+            quest_id: 1,
+            title: "Hunt".to_string(),
+            description: "Into the woods".to_string(),
+            duration_until_join_closed: 1000,
+            duration_in_staking: 100,
+            num_of_nfts: 1,
+            party_size: 0,
+            start_time: 0,
+            create_date: 0,
+            xp_reward: 50,
+            shill_reward: Uint128::from(10u32),
+            collections: vec![],
+            min_level: None,
+            max_level: None,
+            required_traits: vec![],
+            bonus_rules: vec![],
+            max_bonus_reward: None,
+            abandon_policy: AbandonPolicy::default(),
+            outcome_rules: None,
+            loot_tables: vec![],
+            entry_fee: None,
+            reward_pool: Uint128::zero(),
+            reserved: Uint128::zero(),
+            wolves_on_the_hunt: 0,
+            wolves_completed: 0,
+            closed: false
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TraitComparison {
+    Eq,
+    Ne,
+    /// numeric comparisons, a trait value that is not a number never matches
+    Gt,
+    Gte,
+    Lt,
+    Lte
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct TraitCondition {
+    pub trait_type: String,
+    pub comparison: TraitComparison,
+    pub value: String
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    Any,
+    All
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct BonusRule {
+    pub conditions: Vec<TraitCondition>,
+    pub match_mode: MatchMode,
+    pub shill_bonus: Uint128
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct AbandonPolicy {
+    /// percentage (0-100) of the quest's xp_reward still applied to an abandoned wolf
+    pub xp_percent: u8,
+    /// SHILL fee per abandoned wolf, taken from what the owner paid in with PayEntryFee
+    pub shill_fee: Uint128
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct OutcomeRules {
+    /// chance in percent that a hunt succeeds, failed hunts earn no SHILL or XP
+    pub success_chance: u8,
+    /// chance in percent that a successful hunt is a critical success
+    pub critical_chance: u8,
+    /// multiplier applied to the SHILL and XP of a critical success
+    pub critical_multiplier: u32,
+    /// XP earned is rolled between these percentages of the quest's xp_reward
+    pub min_xp_percent: u32,
+    pub max_xp_percent: u32
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HuntOutcome {
+    Success,
+    CriticalSuccess,
+    Failure
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct HuntRoll {
+    pub outcome: HuntOutcome,
+    /// success roll between 0 and 99
+    pub roll: u8,
+    pub xp_percent: u32
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct EntryFee {
+    /// registered SNIP-20 contract the fee is paid in
+    pub token: Addr,
+    /// fee per wolf
+    pub amount: Uint128,
+    /// where the fee goes once the wolf finishes or abandons the quest
+    pub destination: FeeDestination
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeDestination {
+    Treasury {
+        address: Addr
+    },
+    Burn {},
+    /// added to the quest's reward_pool, only for fees paid in SHILL
+    RewardPool {}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LootTable {
+    /// chance in percent that a wolf finds anything in this table
+    pub drop_chance: u8,
+    pub entries: Vec<LootEntry>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LootEntry {
+    /// chance of this entry relative to the other entries of the table
+    pub weight: u32,
+    pub drop: LootDrop
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
+pub enum LootDrop {
+    /// SNIP-20 tokens paid from this contract's balance
+    Snip20 {
+        contract: ContractInfo,
+        amount: Uint128
+    },
+    /// items owned by this contract, each drop hands out the first remaining one
+    Snip721 {
+        contract: ContractInfo,
+        token_ids: Vec<String>
+    },
+    /// a new item minted on a collection this contract is allowed to mint on
+    MintNft {
+        contract: ContractInfo,
+        public_metadata: Option<Metadata>
+    },
+    /// a trait added to the wolf's metadata, or updated if the wolf already has it
+    Trait {
+        trait_type: String,
+        value: String
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    /// pays the entry fee of wolves that are going to join the quest, or the SHILL
+    /// that covers their abandon fee
+    PayEntryFee {
+        quest_id: i32
+    },
+    /// adds SHILL to the quest's reward pool
+    FundQuest {
+        quest_id: i32
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct QuestMsg {
-    pub quest_id: i32
+    pub quest_id: i32,
+    /// party to join on party quests, leave empty to open a new party
+    pub party_id: Option<u32>
 }
  
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -50,8 +290,22 @@ pub struct Token {
     pub token_id: String,
     pub owner: Addr,
     pub sender: Addr,
+    /// address of the SNIP-721 contract the token belongs to
+    pub collection: Addr,
     pub quest_id: i32,
-    pub staked_date: Option<u64>
+    /// None until the wolf's party is full and the hunt starts
+    pub staked_date: Option<u64>,
+    pub party_id: Option<u32>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Party {
+    pub party_id: u32,
+    pub quest_id: i32,
+    pub leader: Addr,
+    pub members: Vec<Addr>,
+    /// set once the party is full, the hunt starts for every member at this time
+    pub formed_date: Option<u64>
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -59,11 +313,27 @@ pub struct HistoryToken {
     pub token_id: String,
     pub owner: Addr,
     pub sender: Addr,
+    pub collection: Addr,
     pub quest_id: i32,
+    pub party_id: Option<u32>,
     pub staked_date: Option<u64>,
     pub claimed_date: Option<u64>,
+    /// total SHILL paid for this wolf (base_reward + bonus_reward)
     pub reward_amount: Uint128,
-    pub xp_reward: i32
+    /// XP applied to this wolf
+    pub xp_reward: i32,
+    /// SHILL paid for completing the quest
+    pub base_reward: Uint128,
+    /// SHILL paid for matching the quest's bonus traits
+    pub bonus_reward: Uint128,
+    /// level of the wolf after the XP was applied, None for history recorded before it was tracked
+    pub level: Option<i32>,
+    /// true when the owner took the wolf back before the quest ended
+    pub abandoned: bool,
+    /// outcome rolled for the wolf on quests with outcome rules
+    pub roll: Option<HuntRoll>,
+    /// loot the wolf brought back
+    pub loot: Vec<LootDrop>
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -74,6 +344,13 @@ pub struct ContractInfo {
     pub address: Addr,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Collection {
+    pub contract: ContractInfo,
+    /// viewing key this contract set on the collection
+    pub viewing_key: String
+}
+
 // #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 // pub struct Requirement {
 //     pub contract_address: String,
@@ -89,6 +366,7 @@ pub struct ContractInfo {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg { 
     StartQuest{
         quest: Quest
@@ -100,24 +378,150 @@ pub enum ExecuteMsg {
     },
     SendNftBack{ 
         token_id: String,
-        owner: Addr
+        owner: Addr,
+        /// defaults to the collection the contract was instantiated with
+        collection: Option<Addr>
     },
     ClaimNfts{ 
-        token_ids: Vec<String>
+        token_ids: Vec<String>,
+        /// defaults to the collection the contract was instantiated with
+        collection: Option<Addr>,
+        /// mixed into the outcome rolls of the claimed wolves
+        entropy: Option<String>
     },
     SetViewingKey{
         key: String
     },
+    /// creates a random viewing key for the sender and returns it
+    CreateViewingKey{
+        entropy: String
+    },
+    /// stops query permits with this name signed by the sender from being accepted
+    RevokePermit{
+        permit_name: String
+    },
     SendShillBack{
         amount: Uint128,
         address: Addr
+    },
+    UpdateQuest{
+        quest_id: i32,
+        update: QuestUpdate
+    },
+    CloseQuest{
+        quest_id: i32
+    },
+    CancelQuest{
+        quest_id: i32
+    },
+    AbandonQuest{
+        token_ids: Vec<String>,
+        /// defaults to the collection the contract was instantiated with
+        collection: Option<Addr>
+    },
+    /// hands out loot found by the sender's wolves, all of it when no index is given
+    ClaimLoot{
+        index: Option<u32>
+    },
+    AddCollection{
+        contract: ContractInfo
+    },
+    /// SNIP-20 receiver, msg is a ReceiveMsg
+    Receive{
+        sender: Addr,
+        from: Addr,
+        amount: Uint128,
+        memo: Option<String>,
+        msg: Option<Binary>
+    },
+    AddFeeToken{
+        contract: ContractInfo
+    },
+    /// returns entry fees paid for a quest that no wolf used yet
+    WithdrawEntryFee{
+        quest_id: i32
+    },
+    /// replaces the whole level table
+    SetLevels{
+        levels: Vec<Level>
+    },
+    /// appends levels to the end of the level table
+    AddLevels{
+        levels: Vec<Level>
+    },
+    SetLevelCap{
+        level_cap: i32
+    },
+    /// sets the metadata traits a wolf's XP and level are kept in
+    SetProgressTraits{
+        xp_trait: String,
+        level_trait: String,
+        private: bool
+    },
+    /// turns writing XP and level to the wolves' metadata on or off
+    SetMetadataSync{
+        sync: bool
+    },
+    /// offers the ownership of the contract, it only moves once accepted
+    ProposeOwner{
+        address: Addr
+    },
+    AcceptOwnership{},
+    GrantRole{
+        address: Addr,
+        role: Role
+    },
+    RevokeRole{
+        address: Addr,
+        role: Role
     }
 }
 
+/// Changes to a quest no wolf joined yet, fields left out are kept
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema, Default)]
+pub struct QuestUpdate {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub xp_reward: Option<i32>,
+    pub shill_reward: Option<Uint128>,
+    pub bonus_rules: Option<Vec<BonusRule>>,
+    pub max_bonus_reward: Option<Uint128>,
+    /// removes the cap on the bonus reward
+    #[serde(default)]
+    pub clear_max_bonus_reward: bool
+}
+
+/// Admin roles the owner can hand out, the owner itself holds every role
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// creates, edits, closes and cancels quests
+    QuestManager,
+    /// sends SHILL out of the contract
+    Treasurer,
+    /// returns staked wolves to their owners
+    Operator
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg { 
-    GetQuests {},
+    GetQuests {
+        start_page: u32,
+        page_size: u32
+    },
+    GetQuest {
+        quest_id: i32
+    },
+    GetCollections {},
+    GetLevels {},
+    GetParty {
+        party_id: u32
+    },
+    GetParties {
+        start_page: u32,
+        page_size: u32
+    },
     GetState {
         viewer: ViewerInfo
     },
@@ -139,11 +543,120 @@ pub enum QueryMsg {
     },
     GetNumUserStakedNftHistory{ 
         permit: Permit
+    },
+    GetUserEntryFee{
+        permit: Permit,
+        quest_id: i32
+    },
+    GetSolvency{
+        viewer: ViewerInfo
+    },
+    /// XP and level of wolves as tracked by the contract, the first collection is used when none is given
+    GetWolfProgress{
+        collection: Option<Addr>,
+        token_ids: Vec<String>
+    },
+    /// whether a wolf is on a quest and when it can be claimed, the first collection is used when none is given
+    GetTokenStatus{
+        token_id: String,
+        collection: Option<Addr>
+    },
+    /// wolves currently on a quest
+    GetQuestParticipants{
+        quest_id: i32,
+        page: u32,
+        page_size: u32
+    },
+    /// user queries authenticated with a query permit
+    WithPermit{
+        permit: Permit,
+        query: UserQuery
+    },
+    /// user queries authenticated with the viewing key set by SetViewingKey
+    WithViewingKey{
+        viewer: ViewerInfo,
+        query: UserQuery
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteAnswer {
+    CreateViewingKey {
+        key: String
+    }
+}
+
+/// Queries about the wolves of the authenticated user
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UserQuery {
+    GetUserStakedNfts {},
+    GetUserStakedNftHistory {
+        start_page: u32,
+        page_size: u32
+    },
+    GetNumUserStakedNftHistory {},
+    GetUserEntryFee {
+        quest_id: i32
+    },
+    /// loot found by the user's wolves that wasn't claimed yet
+    GetPendingLoot {}
+}
+
 // We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LevelsResponse {
+    pub levels: Vec<Level>,
+    pub level_cap: i32
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct WolfProgressResponse {
+    pub token_id: String,
+    /// None for wolves that never finished a quest
+    pub progress: Option<WolfProgress>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct QuestParticipantsResponse {
+    pub participants: Vec<Token>,
+    /// wolves currently on the quest
+    pub active: i32,
+    /// wolves that were claimed after finishing the quest
+    pub completed: i32
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct TokenStatusResponse {
+    pub token_id: String,
+    pub on_quest: bool,
+    pub quest_id: Option<i32>,
+    pub party_id: Option<u32>,
+    /// None while the wolf waits for its party to fill up
+    pub staked_date: Option<u64>,
+    pub claimable_at: Option<u64>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SolvencyResponse {
+    /// SHILL held by the contract
+    pub balance: Uint128,
+    /// SHILL in quest reward pools that isn't reserved yet
+    pub reward_pools: Uint128,
+    /// SHILL reserved for wolves on the hunt
+    pub reserved: Uint128,
+    /// SHILL entry fees held for players
+    pub entry_fees: Uint128,
+    /// SHILL loot found but not claimed yet
+    pub loot: Uint128,
+    /// balance that isn't owed to anyone, zero when the contract is short
+    pub free_balance: Uint128,
+    pub solvent: bool
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct QuestResponse {
     pub quests: Vec<Quest>,
+    pub total: u32
 }
@@ -0,0 +1,146 @@
+use cosmwasm_std::{OverflowError, OverflowOperation, StdError, Uint128};
+use crate::error::ContractError;
+use crate::msg::{ OutcomeRules, HuntOutcome, HuntRoll };
+use crate::rand::Prng;
+
+/// Checks the rules of a new quest, chances are percentages and a critical
+/// success pays at least as much as a regular one. The XP of the best roll
+/// has to fit, otherwise claims would fail once the wolves are on the hunt
+pub fn validate_outcome_rules(rules: &OutcomeRules, quest_id: i32, xp_reward: i32) -> Result<(), ContractError> {
+    if rules.success_chance > 100 || rules.critical_chance > 100 || rules.critical_multiplier < 1
+        || rules.min_xp_percent > rules.max_xp_percent {
+        return Err(ContractError::InvalidOutcomeRules { quest_id });
+    }
+    let best = HuntRoll { outcome: HuntOutcome::CriticalSuccess, roll: 0, xp_percent: rules.max_xp_percent };
+    apply_roll(&best, rules, Uint128::zero(), xp_reward)
+        .map_err(|_| ContractError::InvalidOutcomeRules { quest_id })?;
+    Ok(())
+}
+
+/// Rolls the outcome of a finished hunt. The success roll is between 0 and 99,
+/// a hunt succeeds when it is below the rules' success_chance
+pub fn roll_hunt(rng: &mut Prng, rules: &OutcomeRules) -> HuntRoll {
+    let roll = (rng.next_u32() % 100) as u8;
+    let critical_roll = (rng.next_u32() % 100) as u8;
+    let xp_percent = if rules.max_xp_percent > rules.min_xp_percent {
+            rules.min_xp_percent + rng.next_u32() % (rules.max_xp_percent - rules.min_xp_percent + 1)
+        }
+        else {
+            rules.min_xp_percent
+        };
+
+    let outcome = if roll >= rules.success_chance {
+            HuntOutcome::Failure
+        }
+        else if critical_roll < rules.critical_chance {
+            HuntOutcome::CriticalSuccess
+        }
+        else {
+            HuntOutcome::Success
+        };
+
+    HuntRoll { outcome, roll, xp_percent }
+}
+
+/// Applies a roll to the SHILL and XP a wolf earns. Failed hunts earn nothing,
+/// critical successes multiply both rewards
+pub fn apply_roll(roll: &HuntRoll, rules: &OutcomeRules, shill: Uint128, xp: i32) -> Result<(Uint128, i32), ContractError> {
+    let overflow = |factor: u32| StdError::overflow(OverflowError::new(OverflowOperation::Mul, xp, factor));
+    let xp = i32::try_from(xp as i64 * roll.xp_percent as i64 / 100).map_err(|_| overflow(roll.xp_percent))?;
+    Ok(match roll.outcome {
+        HuntOutcome::Failure => (Uint128::zero(), 0),
+        HuntOutcome::Success => (shill, xp),
+        HuntOutcome::CriticalSuccess => (
+            shill.checked_mul(Uint128::from(rules.critical_multiplier)).map_err(StdError::from)?,
+            i32::try_from(rules.critical_multiplier).ok()
+                .and_then(|multiplier| xp.checked_mul(multiplier))
+                .ok_or_else(|| overflow(rules.critical_multiplier))?
+        )
+    })
+}
+
+pub fn outcome_name(outcome: &HuntOutcome) -> &'static str {
+    match outcome {
+        HuntOutcome::Success => "success",
+        HuntOutcome::CriticalSuccess => "critical_success",
+        HuntOutcome::Failure => "failure"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(success_chance: u8, critical_chance: u8) -> OutcomeRules {
+        OutcomeRules {
+            success_chance,
+            critical_chance,
+            critical_multiplier: 2,
+            min_xp_percent: 50,
+            max_xp_percent: 150
+        }
+    }
+
+    #[test]
+    fn chances_decide_the_outcome() {
+        let mut rng = Prng::new(b"seed", b"claim");
+        for _ in 0..20 {
+            assert_eq!(HuntOutcome::Failure, roll_hunt(&mut rng, &rules(0, 100)).outcome);
+            assert_eq!(HuntOutcome::Success, roll_hunt(&mut rng, &rules(100, 0)).outcome);
+            assert_eq!(HuntOutcome::CriticalSuccess, roll_hunt(&mut rng, &rules(100, 100)).outcome);
+        }
+    }
+
+    #[test]
+    fn rolls_stay_in_range() {
+        let mut rng = Prng::new(b"seed", b"claim");
+        for _ in 0..100 {
+            let roll = roll_hunt(&mut rng, &rules(50, 10));
+            assert!(roll.roll < 100);
+            assert!(roll.xp_percent >= 50 && roll.xp_percent <= 150);
+        }
+
+        let mut fixed = rules(50, 10);
+        fixed.max_xp_percent = 0;
+        assert_eq!(50, roll_hunt(&mut rng, &fixed).xp_percent);
+    }
+
+    #[test]
+    fn same_seed_same_roll() {
+        let first = roll_hunt(&mut Prng::new(b"seed", b"claim"), &rules(50, 10));
+        let second = roll_hunt(&mut Prng::new(b"seed", b"claim"), &rules(50, 10));
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn rewards_follow_the_outcome() {
+        let shill = Uint128::from(10u32);
+        let roll = |outcome| HuntRoll { outcome, roll: 0, xp_percent: 150 };
+        assert_eq!((Uint128::zero(), 0), apply_roll(&roll(HuntOutcome::Failure), &rules(50, 10), shill, 40).unwrap());
+        assert_eq!((shill, 60), apply_roll(&roll(HuntOutcome::Success), &rules(50, 10), shill, 40).unwrap());
+        assert_eq!((Uint128::from(20u32), 120), apply_roll(&roll(HuntOutcome::CriticalSuccess), &rules(50, 10), shill, 40).unwrap());
+
+        let mut huge = rules(50, 10);
+        huge.critical_multiplier = u32::MAX;
+        assert!(apply_roll(&roll(HuntOutcome::CriticalSuccess), &huge, shill, 40).is_err());
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        assert_eq!(Ok(()), validate_outcome_rules(&rules(50, 10), 1, 40));
+        let invalid = || Err(ContractError::InvalidOutcomeRules { quest_id: 1 });
+        assert_eq!(invalid(), validate_outcome_rules(&rules(101, 10), 1, 40));
+        assert_eq!(invalid(), validate_outcome_rules(&rules(50, 101), 1, 40));
+
+        let mut no_critical = rules(50, 10);
+        no_critical.critical_multiplier = 0;
+        assert_eq!(invalid(), validate_outcome_rules(&no_critical, 1, 40));
+
+        let mut inverted = rules(50, 10);
+        inverted.min_xp_percent = 200;
+        assert_eq!(invalid(), validate_outcome_rules(&inverted, 1, 40));
+
+        // the best roll earns 150% of the XP twice
+        assert_eq!(invalid(), validate_outcome_rules(&rules(50, 10), 1, i32::MAX / 2));
+    }
+}
@@ -13,6 +13,14 @@ pub fn sha_256(data: &[u8]) -> [u8; 32] {
     result
 }
 
+/// Compares two byte slices in time that only depends on their length
+pub fn ct_slice_compare(s1: &[u8], s2: &[u8]) -> bool {
+    if s1.len() != s2.len() {
+        return false;
+    }
+    s1.iter().zip(s2.iter()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
 pub struct Prng {
     rng: ChaChaRng,
 }
@@ -22,8 +30,8 @@ impl Prng {
         let mut hasher = Sha256::new();
 
         // write input message
-        hasher.update(&seed);
-        hasher.update(&entropy);
+        hasher.update(seed);
+        hasher.update(entropy);
         let hash = hasher.finalize();
 
         let mut hash_bytes = [0u8; 32];
@@ -40,6 +48,10 @@ impl Prng {
 
         bytes
     }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,74 @@
+use cosmwasm_std::{Addr, StdResult, Uint128};
+use crate::msg::Quest;
+use crate::loot::max_token_loot;
+
+/// Returns the SHILL and XP a single wolf earns on a quest, party quests split
+/// the rewards evenly among the members
+pub fn wolf_rewards(
+    quest: &Quest,
+    party_id: Option<u32>
+) -> (Uint128, i32) {
+    if party_id.is_some() && quest.party_size > 1 {
+        (quest.shill_reward.multiply_ratio(1u32, quest.party_size as u32), quest.xp_reward / quest.party_size)
+    }
+    else {
+        (quest.shill_reward, quest.xp_reward)
+    }
+}
+
+/// Returns the most SHILL a single wolf can earn on a quest, which is reserved
+/// from the reward pool while the wolf is on the hunt. SHILL found as loot is
+/// paid from the same reservation
+pub fn max_wolf_reward(
+    quest: &Quest,
+    party_id: Option<u32>,
+    shill: &Addr
+) -> StdResult<Uint128> {
+    let (base_reward, _) = wolf_rewards(quest, party_id);
+    let base_reward = match &quest.outcome_rules {
+        Some(rules) if rules.critical_chance > 0 => base_reward.checked_mul(Uint128::from(rules.critical_multiplier))?,
+        _ => base_reward
+    };
+    let mut all_bonuses = Uint128::zero();
+    for rule in quest.bonus_rules.iter() {
+        all_bonuses = all_bonuses.checked_add(rule.shill_bonus)?;
+    }
+    let max_bonus = match quest.max_bonus_reward {
+        Some(cap) => std::cmp::min(all_bonuses, cap),
+        None => all_bonuses
+    };
+    let max_loot = max_token_loot(&quest.loot_tables, shill)?;
+    Ok(base_reward.checked_add(max_bonus)?.checked_add(max_loot)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::{OutcomeRules, LootTable, LootEntry, LootDrop, ContractInfo};
+
+    fn critical(critical_multiplier: u32) -> Option<OutcomeRules> {
+        Some(OutcomeRules { success_chance: 100, critical_chance: 10, critical_multiplier, min_xp_percent: 100, max_xp_percent: 100 })
+    }
+
+    #[test]
+    fn critical_hits_are_reserved() {
+        let shill = Addr::unchecked("shilltoken");
+        let mut quest = Quest { outcome_rules: critical(3), ..Quest::default() };
+        assert_eq!(Uint128::from(30u32), max_wolf_reward(&quest, None, &shill).unwrap());
+        quest.outcome_rules = critical(1);
+        assert_eq!(Uint128::from(10u32), max_wolf_reward(&quest, None, &shill).unwrap());
+    }
+
+    #[test]
+    fn shill_loot_is_reserved() {
+        let shill = ContractInfo { address: Addr::unchecked("shilltoken"), code_hash: "hash".to_string() };
+        let quest = Quest {
+            loot_tables: vec![LootTable {
+                drop_chance: 50,
+                entries: vec![LootEntry { weight: 1, drop: LootDrop::Snip20 { contract: shill.clone(), amount: Uint128::from(4u32) } }]
+            }],
+            ..Quest::default()
+        };
+        assert_eq!(Uint128::from(14u32), max_wolf_reward(&quest, None, &shill.address).unwrap());
+    }
+}
@@ -1,7 +1,7 @@
 use schemars::JsonSchema;
 use serde::{ Deserialize, Serialize};
 
-use cosmwasm_std::{CanonicalAddr, Addr, Storage};
+use cosmwasm_std::{CanonicalAddr, Addr, Storage, Uint128};
 use cosmwasm_storage::{
     singleton, singleton_read, ReadonlySingleton, Singleton,
 };
@@ -9,43 +9,177 @@ use secret_toolkit::{
     storage:: { Item, Keymap, AppendStore },
     snip721:: { ViewerInfo }
 };
-use crate::msg::{Quest, Token, HistoryToken, ContractInfo, Level};
+use crate::msg::{Quest, Token, HistoryToken, ContractInfo, Collection, Level, Party, WolfProgress, Role, LootDrop};
 
 pub static CONFIG_KEY: &[u8] = b"config";
 pub const LEVEL_KEY: &[u8] = b"level";
+pub const QUESTS_KEY: &[u8] = b"quests";
+pub const CONTRACT_VERSION_KEY: &[u8] = b"contract_version";
+pub const PRNG_SEED_KEY: &[u8] = b"prng_seed";
 pub const ADMIN_KEY: &[u8] = b"admin";
 pub const MY_ADDRESS_KEY: &[u8] = b"my_address";
 pub const ADMIN_VIEWING_KEY: &[u8] = b"admin_viewing_key";
 pub const VIEWING_KEY: &[u8] = b"viewing_key";
 pub const STAKED_NFTS_KEY: &[u8] = b"staked";
 pub const STAKED_NFTS_HISTORY_KEY: &[u8] = b"staked_history";
+pub const PARTIES_KEY: &[u8] = b"parties";
+pub const PARTY_COUNT_KEY: &[u8] = b"party_count";
+pub const PENDING_LOOT_KEY: &[u8] = b"pending_loot";
+pub const ENTRY_FEES_KEY: &[u8] = b"entry_fees";
+pub const WOLF_PROGRESS_KEY: &[u8] = b"wolf_progress";
+pub const FUNDS_KEY: &[u8] = b"funds";
+pub const TOKEN_INDEX_KEY: &[u8] = b"token_index";
+pub const QUEST_PARTICIPANTS_KEY: &[u8] = b"quest_participants";
 pub const PREFIX_REVOKED_PERMITS: &str = "revoke";
 
+/// Version of the storage layout written by this code. Contracts instantiated
+/// before versioning was introduced have no stored version and are treated as 1.
+pub const CONTRACT_VERSION: u32 = 2;
+
 pub static CONFIG_ITEM: Item<State> = Item::new(CONFIG_KEY);
 pub static LEVEL_ITEM: Item<Vec<Level>> = Item::new(LEVEL_KEY);
+pub static CONTRACT_VERSION_ITEM: Item<u32> = Item::new(CONTRACT_VERSION_KEY);
+pub static PRNG_SEED_ITEM: Item<Vec<u8>> = Item::new(PRNG_SEED_KEY);
 pub static ADMIN_ITEM: Item<CanonicalAddr> = Item::new(ADMIN_KEY);
 pub static ADMIN_VIEWING_KEY_ITEM: Item<ViewerInfo> = Item::new(ADMIN_VIEWING_KEY);
 pub static MY_ADDRESS_ITEM: Item<CanonicalAddr> = Item::new(MY_ADDRESS_KEY);
-pub static VIEWING_KEY_STORE: Keymap<CanonicalAddr, ViewerInfo> = Keymap::new(VIEWING_KEY);
-pub static STAKED_NFTS_STORE: Keymap<CanonicalAddr, Vec<Token>> = Keymap::new(STAKED_NFTS_KEY);
 pub static STAKED_NFTS_HISTORY_STORE: AppendStore<HistoryToken> = AppendStore::new(STAKED_NFTS_HISTORY_KEY);
+pub static PARTY_COUNT_ITEM: Item<u32> = Item::new(PARTY_COUNT_KEY);
+pub static ENTRY_FEES_STORE: Keymap<CanonicalAddr, EntryFeeEscrow> = Keymap::new(ENTRY_FEES_KEY);
+pub static WOLF_PROGRESS_STORE: Keymap<String, WolfProgress> = Keymap::new(WOLF_PROGRESS_KEY);
+pub static TOKEN_INDEX_STORE: Keymap<String, Token> = Keymap::new(TOKEN_INDEX_KEY);
+pub static FUNDS_ITEM: Item<Funds> = Item::new(FUNDS_KEY);
+pub static QUEST_PARTICIPANTS_STORE: Keymap<(Addr, String), Addr> = Keymap::new(QUEST_PARTICIPANTS_KEY);
+
+// Keymaps cache their length, so every caller gets a fresh instance instead of
+// sharing a static one
+
+pub fn quests_store() -> Keymap<'static, i32, Quest> {
+    Keymap::new(QUESTS_KEY)
+}
+
+pub fn viewing_key_store() -> Keymap<'static, CanonicalAddr, ViewerInfo> {
+    Keymap::new(VIEWING_KEY)
+}
+
+/// Returns the wolves every user has on quests, keyed by the owner's canonical address
+pub fn staked_nfts_store() -> Keymap<'static, CanonicalAddr, Vec<Token>> {
+    Keymap::new(STAKED_NFTS_KEY)
+}
+
+pub fn parties_store() -> Keymap<'static, u32, Party> {
+    Keymap::new(PARTIES_KEY)
+}
+
+/// Returns the loot users found but didn't claim yet
+pub fn pending_loot_store() -> Keymap<'static, CanonicalAddr, Vec<LootDrop>> {
+    Keymap::new(PENDING_LOOT_KEY)
+}
+
+/// Returns the staking history of a user. History is always keyed by the
+/// canonical address of the wolves' owner.
+pub fn staked_history_store(owner_raw: &CanonicalAddr) -> AppendStore<'static, HistoryToken> {
+    STAKED_NFTS_HISTORY_STORE.add_suffix(owner_raw.as_slice())
+}
+
+/// Returns the entry fees users paid for a quest
+pub fn entry_fee_store(quest_id: i32) -> Keymap<'static, CanonicalAddr, EntryFeeEscrow> {
+    ENTRY_FEES_STORE.add_suffix(&quest_id.to_be_bytes())
+}
+
+/// Returns the XP and level of a collection's wolves, keyed by token id
+pub fn wolf_progress_store(collection: &Addr) -> Keymap<'static, String, WolfProgress> {
+    WOLF_PROGRESS_STORE.add_suffix(collection.as_bytes())
+}
+
+/// Returns the wolves of a collection that are on a quest, keyed by token id.
+/// Every entry mirrors the token in its owner's `staked_nfts_store` list
+pub fn token_index_store(collection: &Addr) -> Keymap<'static, String, Token> {
+    TOKEN_INDEX_STORE.add_suffix(collection.as_bytes())
+}
+
+/// Returns the wolves on a quest, keyed by collection and token id and mapped
+/// to their owner. The wolves themselves are read from `token_index_store`
+pub fn quest_participants_store(quest_id: i32) -> Keymap<'static, (Addr, String), Addr> {
+    QUEST_PARTICIPANTS_STORE.add_suffix(&quest_id.to_be_bytes())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct RoleGrant {
+    pub address: Addr,
+    pub role: Role
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct EntryFeeEscrow {
+    /// fees paid that no wolf used yet
+    pub paid: Uint128,
+    /// fees of wolves still on the quest
+    pub locked: Uint128
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct State { 
-    pub quests: Vec<Quest>,
     pub locked_nfts: Vec<Token>,
     pub owner: Addr, 
-    pub viewing_key: Option<String>,
-    pub quest_contract: ContractInfo, 
+    /// SNIP-721 contracts wolves can be sent from, the first one is the collection
+    /// the contract was instantiated with
+    pub collections: Vec<Collection>,
     pub level_cap: i32,
     pub shill_viewing_key: Option<String>,
-    pub shill_contract: ContractInfo
+    pub shill_contract: ContractInfo,
+    /// SNIP-20 contracts entry fees can be paid in
+    pub fee_tokens: Vec<ContractInfo>,
+    /// metadata trait types a wolf's XP and level are kept in
+    pub xp_trait: String,
+    pub level_trait: String,
+    /// keeps XP and level in the private metadata instead of the public one
+    pub private_progress: bool,
+    /// writes XP and level to the wolves' metadata, the contract's own
+    /// ledger is authoritative either way
+    pub sync_metadata: bool,
+    /// address the owner offered the contract to
+    pub pending_owner: Option<Addr>,
+    pub roles: Vec<RoleGrant>
+}
+
+/// Running totals of the SHILL the contract owes, updated whenever a quest or
+/// an escrow is saved so checking solvency never has to read every quest
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct Funds {
+    /// SHILL in quest reward pools that isn't reserved yet
+    pub reward_pools: Uint128,
+    /// SHILL reserved for wolves on the hunt
+    pub reserved: Uint128,
+    /// SHILL paid into quest escrows
+    pub entry_fees: Uint128,
+    /// SHILL loot players found but didn't claim yet
+    pub loot: Uint128
+}
+
+impl State {
+    pub fn collection(&self, address: &Addr) -> Option<&Collection> {
+        self.collections.iter().find(|x| &x.contract.address == address)
+    }
+
+    pub fn fee_token(&self, address: &Addr) -> Option<&ContractInfo> {
+        self.fee_tokens.iter().find(|x| &x.address == address)
+    }
+
+    /// The owner holds every role
+    pub fn has_role(&self, address: &Addr, role: Role) -> bool {
+        address == &self.owner || self.roles.iter().any(|x| &x.address == address && x.role == role)
+    }
+
+    pub fn is_admin(&self, address: &Addr) -> bool {
+        address == &self.owner || self.roles.iter().any(|x| &x.address == address)
+    }
 }
 
-pub fn config(storage: &mut dyn Storage) -> Singleton<State> {
+pub fn config(storage: &mut dyn Storage) -> Singleton<'_, State> {
     singleton(storage, CONFIG_KEY)
 }
 
-pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<State> {
+pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<'_, State> {
     singleton_read(storage, CONFIG_KEY)
 }
\ No newline at end of file